@@ -0,0 +1,366 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use serde_json::{json, Value};
+
+use crate::{
+    components::{Despawn, Enemy, EnemyKind, Explosion, FromPlayer, Health, Immunity, Laser, Player, Velocity},
+    difficulty::Difficulty,
+    enemy::{Boss, BossHealthBar, BossPart, Director, SpawnTelegraph, Waves},
+    input::{InputSource, PlayerActions},
+    shield::{Shield, ShieldAbility},
+    stats::GameStats,
+    tuning::Tuning,
+    GameTime, PlayerState, TIME_STEP,
+};
+
+// Reward per point of score gained, and penalty per point of damage taken
+const SCORE_REWARD: f64 = 1.;
+const DAMAGE_PENALTY: f64 = 1.;
+
+/// Where the external agent talks to the game.
+#[derive(Clone, Debug)]
+pub enum AgentTransport {
+    Stdio,
+    Tcp(String),
+}
+
+/// Step-based control for external agents (reset / step(action) ->
+/// observation, reward, done), one JSON object per line.
+///
+/// Requests: `{"cmd": "reset"}` or
+/// `{"cmd": "step", "action": {"move": [x, y], "aim": angle | null, "shield": bool}, "frames": n}`.
+pub struct AgentPlugin {
+    pub transport: AgentTransport,
+}
+
+impl Plugin for AgentPlugin {
+    fn build(&self, app: &mut App) {
+        let (request_tx, request_rx) = mpsc::channel();
+        match &self.transport {
+            AgentTransport::Stdio => spawn_stdio(request_tx),
+            AgentTransport::Tcp(addr) => spawn_tcp(addr.clone(), request_tx),
+        }
+        app.insert_resource(AgentLink {
+            requests: Mutex::new(request_rx),
+            responses: None,
+            pending: None,
+            last_score: 0.,
+            last_damage: 0.,
+        })
+        .insert_resource(InputSource::Agent)
+        .add_startup_system(agent_setup_system)
+        .add_system_to_stage(CoreStage::PreUpdate, agent_system);
+    }
+}
+
+/// What the transport threads tell `agent_system`.
+enum AgentMessage {
+    /// An agent connected, responses go to it from now on.
+    Connected(Sender<String>),
+    Request(String),
+    /// The agent went away; dropping its response sender ends its writer.
+    Disconnected,
+}
+
+/// Forward one agent's requests until it goes away, returns false once the
+/// game is gone.
+fn forward_lines(reader: impl BufRead, request_tx: &Sender<AgentMessage>) -> bool {
+    for line in reader.lines().map_while(Result::ok) {
+        if request_tx.send(AgentMessage::Request(line)).is_err() {
+            return false;
+        }
+    }
+    request_tx.send(AgentMessage::Disconnected).is_ok()
+}
+
+/// Writes responses until `agent_system` drops the sender or the agent stops listening.
+fn spawn_writer(mut writer: impl Write + Send + 'static) -> Sender<String> {
+    let (response_tx, response_rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for response in response_rx {
+            if writeln!(writer, "{}", response).and_then(|_| writer.flush()).is_err() {
+                break;
+            }
+        }
+    });
+    response_tx
+}
+
+fn spawn_stdio(request_tx: Sender<AgentMessage>) {
+    thread::spawn(move || {
+        if request_tx.send(AgentMessage::Connected(spawn_writer(io::stdout()))).is_ok() {
+            forward_lines(io::stdin().lock(), &request_tx);
+        }
+    });
+}
+
+fn spawn_tcp(addr: String, request_tx: Sender<AgentMessage>) {
+    thread::spawn(move || {
+        let listener = match TcpListener::bind(&addr) {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!("agent: cannot listen on {}: {}", addr, err);
+                return;
+            }
+        };
+        info!("agent: listening on {}", addr);
+        // one agent at a time; the next connection takes over when it leaves
+        for stream in listener.incoming().flatten() {
+            let writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(_) => continue,
+            };
+            if request_tx.send(AgentMessage::Connected(spawn_writer(writer))).is_err()
+                || !forward_lines(BufReader::new(stream), &request_tx)
+            {
+                return;
+            }
+            info!("agent: disconnected, waiting for the next one");
+        }
+    });
+}
+
+struct PendingStep {
+    frames_left: u64,
+}
+
+struct AgentLink {
+    requests: Mutex<Receiver<AgentMessage>>,
+    /// The connected agent, if any.
+    responses: Option<Mutex<Sender<String>>>,
+    pending: Option<PendingStep>,
+    last_score: f64,
+    last_damage: f32,
+}
+
+impl AgentLink {
+    fn send(&self, value: Value) {
+        if let Some(responses) = &self.responses {
+            let _ = responses.lock().unwrap().send(value.to_string());
+        }
+    }
+}
+
+/// Everything a `reset` clears away.
+type EpisodeEntity = Or<(
+    With<Enemy>,
+    With<Laser>,
+    With<Explosion>,
+    With<Player>,
+    With<Boss>,
+    With<BossHealthBar>,
+    With<SpawnTelegraph>,
+)>;
+
+type PlayerObservationQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Health,
+        &'static Immunity,
+        &'static Shield,
+        &'static ShieldAbility,
+    ),
+    With<Player>,
+>;
+type EnemyObservationQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, Option<&'static EnemyKind>, Option<&'static BossPart>, &'static Health),
+    With<Enemy>,
+>;
+type LaserObservationQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static Velocity, Option<&'static FromPlayer>), With<Laser>>;
+
+/// Everything a `reset` puts back to the start of an episode.
+#[derive(SystemParam)]
+struct Episode<'w, 's> {
+    commands: Commands<'w, 's>,
+    actions: ResMut<'w, PlayerActions>,
+    player_state: ResMut<'w, PlayerState>,
+    stats: ResMut<'w, GameStats>,
+    game_time: ResMut<'w, GameTime>,
+    tuning: Res<'w, Tuning>,
+    difficulty: ResMut<'w, Difficulty>,
+    waves: ResMut<'w, Waves>,
+    director: ResMut<'w, Director>,
+    world_query: Query<'w, 's, Entity, EpisodeEntity>,
+}
+
+impl<'w, 's> Episode<'w, 's> {
+    fn reset(&mut self, link: &mut AgentLink) {
+        for entity in self.world_query.iter() {
+            self.commands.entity(entity).insert(Despawn);
+        }
+        *self.player_state = PlayerState::new(&self.tuning);
+        let respawn = self.player_state.spawn_cooldown.duration();
+        self.player_state.spawn_cooldown.tick(respawn);
+        *self.stats = GameStats::default();
+        *self.difficulty = Difficulty::new(self.tuning.difficulty);
+        *self.waves = Waves::default();
+        *self.director = Director::new(&self.tuning);
+        *self.actions = PlayerActions::default();
+        // scripts and anything else going by the clock start over too
+        self.game_time.restart();
+        link.last_score = 0.;
+        link.last_damage = 0.;
+    }
+}
+
+fn agent_setup_system(mut game_time: ResMut<GameTime>) {
+    // one step advances the simulation by a fixed, reproducible amount
+    game_time.fixed_step = Some(std::time::Duration::from_secs_f32(TIME_STEP));
+}
+
+/// Runs first every frame: finishes the step in flight once its frames have
+/// run, then blocks until the agent sends the next request.
+fn agent_system(
+    mut link: ResMut<AgentLink>,
+    mut episode: Episode,
+    mut exit: EventWriter<AppExit>,
+    player_query: PlayerObservationQuery,
+    enemy_query: EnemyObservationQuery,
+    laser_query: LaserObservationQuery,
+) {
+    if let Some(step) = &mut link.pending {
+        step.frames_left = step.frames_left.saturating_sub(1);
+        if step.frames_left > 0 {
+            return;
+        }
+        link.pending = None;
+
+        let stats = &episode.stats;
+        let score = episode.player_state.score;
+        // score starts over when the player respawns
+        let gained = if score >= link.last_score { score - link.last_score } else { score };
+        let reward = gained * SCORE_REWARD - (stats.damage_taken - link.last_damage) as f64 * DAMAGE_PENALTY;
+        let (damage_taken, done) = (stats.damage_taken, stats.deaths > 0);
+        link.last_score = score;
+        link.last_damage = damage_taken;
+        let observation = observe(&episode.player_state, &player_query, &enemy_query, &laser_query);
+        link.send(json!({
+            "observation": observation,
+            "reward": reward,
+            "done": done,
+        }));
+    }
+
+    loop {
+        let message = link.requests.lock().unwrap().recv();
+        let line = match message {
+            Ok(AgentMessage::Request(line)) => line,
+            Ok(AgentMessage::Connected(responses)) => {
+                link.responses = Some(Mutex::new(responses));
+                continue;
+            }
+            Ok(AgentMessage::Disconnected) => {
+                // start the next agent, if one comes, on a fresh episode
+                link.responses = None;
+                episode.reset(&mut link);
+                continue;
+            }
+            Err(_) => {
+                // no agent can come any more
+                exit.send(AppExit);
+                return;
+            }
+        };
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                link.send(json!({ "error": format!("invalid request: {}", err) }));
+                continue;
+            }
+        };
+        match request["cmd"].as_str() {
+            Some("reset") => {
+                episode.reset(&mut link);
+                // one frame for the old entities to go and the player to spawn
+                link.pending = Some(PendingStep { frames_left: 1 });
+                return;
+            }
+            Some("step") => {
+                let action = &request["action"];
+                let movement = &action["move"];
+                let actions = &mut episode.actions;
+                actions.movement = Vec2::new(
+                    movement[0].as_f64().unwrap_or(0.) as f32,
+                    movement[1].as_f64().unwrap_or(0.) as f32,
+                );
+                actions.aim = action["aim"].as_f64().map(|aim| aim as f32);
+                actions.shield = action["shield"].as_bool().unwrap_or(false);
+                let frames = request["frames"].as_u64().unwrap_or(1).max(1);
+                link.pending = Some(PendingStep { frames_left: frames });
+                return;
+            }
+            _ => link.send(json!({ "error": "expected cmd 'reset' or 'step'" })),
+        }
+    }
+}
+
+fn observe(
+    player_state: &PlayerState,
+    player_query: &PlayerObservationQuery,
+    enemy_query: &EnemyObservationQuery,
+    laser_query: &LaserObservationQuery,
+) -> Value {
+    let player = player_query.get_single().ok().map(|(tf, health, immunity, shield, ability)| {
+        json!({
+            "x": tf.translation.x,
+            "y": tf.translation.y,
+            "vx": player_state.delta_x,
+            "vy": player_state.delta_y,
+            "angle": player_state.angle,
+            "hp": health.hp,
+            "immune": immunity.active(),
+            "shield": shield.hp,
+            "shield_ready": ability.is_ready(),
+            "fire_ready": player_state.fire_cooldown.finished(),
+        })
+    });
+    let enemies: Vec<Value> = enemy_query
+        .iter()
+        .map(|(tf, kind, part, health)| {
+            // boss parts are enemies of their own, named after the part
+            let kind = match (kind, part) {
+                (Some(kind), _) => format!("{:?}", kind),
+                (None, Some(part)) => format!("Boss{:?}", part.kind),
+                (None, None) => "Unknown".to_string(),
+            };
+            json!({
+                "x": tf.translation.x,
+                "y": tf.translation.y,
+                "kind": kind,
+                "hp": health.hp,
+            })
+        })
+        .collect();
+    let lasers: Vec<Value> = laser_query
+        .iter()
+        .map(|(tf, velocity, from_player)| {
+            json!({
+                "x": tf.translation.x,
+                "y": tf.translation.y,
+                "vx": velocity.x,
+                "vy": velocity.y,
+                "from_player": from_player.is_some(),
+            })
+        })
+        .collect();
+    json!({
+        "player": player,
+        "enemies": enemies,
+        "lasers": lasers,
+        "score": player_state.score,
+    })
+}
@@ -0,0 +1,221 @@
+use std::str::FromStr;
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    components::{Laser, Movable, Player, SpriteSize},
+    tuning::Tuning,
+    PlayerState, WinSize,
+};
+
+// Share of the player's speed kept when bouncing off a wall
+const BOUNCE_RESTITUTION: f32 = 0.8;
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        let tuning = app.world.get_resource::<Tuning>().cloned().unwrap_or_default();
+        app.insert_resource(tuning.boundary)
+            .add_system(boundary_mode_keyboard_system)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .before(TransformSystem::TransformPropagate)
+                    .with_system(player_boundary_system)
+                    .with_system(projectile_wrap_system)
+                    .with_system(seam_ghost_spawn_system)
+                    .with_system(seam_ghost_system.after(player_boundary_system).after(projectile_wrap_system)),
+            );
+    }
+}
+
+/// How the play-field edges treat the player (and, for `Wrap`, lasers).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BoundaryMode {
+    /// Stop at the edge, killing velocity into the wall.
+    #[default]
+    Clamp,
+    /// Reflect off the edge, losing some speed.
+    Bounce,
+    /// Leave one side and come back on the opposite one.
+    Wrap,
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "clamp" => Ok(BoundaryMode::Clamp),
+            "bounce" => Ok(BoundaryMode::Bounce),
+            "wrap" => Ok(BoundaryMode::Wrap),
+            _ => Err(format!("unknown boundary mode '{}'", s)),
+        }
+    }
+}
+
+impl BoundaryMode {
+    fn next(self) -> Self {
+        match self {
+            BoundaryMode::Clamp => BoundaryMode::Bounce,
+            BoundaryMode::Bounce => BoundaryMode::Wrap,
+            BoundaryMode::Wrap => BoundaryMode::Clamp,
+        }
+    }
+}
+
+/// Projectile that already crossed the seam once, it despawns off-screen next time.
+#[derive(Component)]
+struct Wrapped;
+
+/// Marks an entity whose seam ghosts have been spawned.
+#[derive(Component)]
+struct HasSeamGhosts;
+
+/// Copy of the parent sprite drawn on the far side of the seam.
+/// The value selects the axes it is offset along.
+#[derive(Component)]
+struct SeamGhost(Vec2);
+
+fn boundary_mode_keyboard_system(kb: Res<Input<KeyCode>>, mut mode: ResMut<BoundaryMode>) {
+    if kb.just_pressed(KeyCode::F3) {
+        *mode = mode.next();
+    }
+}
+
+fn half_extent(transform: &Transform, size: &SpriteSize) -> Vec2 {
+    size.0 * transform.scale.truncate() / 2.
+}
+
+fn player_boundary_system(
+    mode: Res<BoundaryMode>,
+    win_size: Res<WinSize>,
+    mut player_state: ResMut<PlayerState>,
+    mut query: Query<(&mut Transform, &SpriteSize), With<Player>>,
+) {
+    let half_field = Vec2::new(win_size.w, win_size.h) / 2.;
+    if let Ok((mut transform, size)) = query.get_single_mut() {
+        let limit = half_field - half_extent(&transform, size);
+        let translation = &mut transform.translation;
+        match *mode {
+            BoundaryMode::Wrap => {
+                if translation.x.abs() > half_field.x {
+                    translation.x -= translation.x.signum() * win_size.w;
+                }
+                if translation.y.abs() > half_field.y {
+                    translation.y -= translation.y.signum() * win_size.h;
+                }
+            }
+            BoundaryMode::Clamp | BoundaryMode::Bounce => {
+                let bounce = if *mode == BoundaryMode::Bounce { -BOUNCE_RESTITUTION } else { 0. };
+                if translation.x.abs() > limit.x {
+                    translation.x = translation.x.clamp(-limit.x, limit.x);
+                    player_state.delta_x *= bounce;
+                }
+                if translation.y.abs() > limit.y {
+                    translation.y = translation.y.clamp(-limit.y, limit.y);
+                    player_state.delta_y *= bounce;
+                }
+            }
+        }
+    }
+}
+
+type UnwrappedLaserQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut Transform, &'static Movable), (With<Laser>, Without<Wrapped>)>;
+
+fn projectile_wrap_system(
+    mut commands: Commands,
+    mode: Res<BoundaryMode>,
+    win_size: Res<WinSize>,
+    mut query: UnwrappedLaserQuery,
+) {
+    if *mode != BoundaryMode::Wrap {
+        return;
+    }
+    let half_field = Vec2::new(win_size.w, win_size.h) / 2.;
+    for (entity, mut transform, movable) in query.iter_mut() {
+        if !movable.auto_despawn {
+            continue;
+        }
+        let translation = &mut transform.translation;
+        let mut wrapped = false;
+        if translation.x.abs() > half_field.x {
+            translation.x -= translation.x.signum() * win_size.w;
+            wrapped = true;
+        }
+        if translation.y.abs() > half_field.y {
+            translation.y -= translation.y.signum() * win_size.h;
+            wrapped = true;
+        }
+        if wrapped {
+            commands.entity(entity).insert(Wrapped);
+        }
+    }
+}
+
+/// Everything that can cross the seam: the player and lasers.
+type SeamCrosserQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Handle<Image>),
+    (Or<(With<Player>, With<Laser>)>, With<SpriteSize>, Without<HasSeamGhosts>),
+>;
+
+fn seam_ghost_spawn_system(mut commands: Commands, mode: Res<BoundaryMode>, query: SeamCrosserQuery) {
+    if *mode != BoundaryMode::Wrap {
+        return;
+    }
+    for (entity, texture) in query.iter() {
+        commands.entity(entity).insert(HasSeamGhosts).with_children(|parent| {
+            for axes in [Vec2::X, Vec2::Y, Vec2::ONE] {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        texture: texture.clone(),
+                        visibility: Visibility { is_visible: false },
+                        ..Default::default()
+                    })
+                    .insert(SeamGhost(axes));
+            }
+        });
+    }
+}
+
+type SeamParentQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static SpriteSize, &'static Sprite), (With<HasSeamGhosts>, Without<SeamGhost>)>;
+type SeamGhostQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Parent, &'static SeamGhost, &'static mut Transform, &'static mut Sprite, &'static mut Visibility),
+>;
+
+fn seam_ghost_system(
+    mode: Res<BoundaryMode>,
+    win_size: Res<WinSize>,
+    parent_query: SeamParentQuery,
+    mut ghost_query: SeamGhostQuery,
+) {
+    let half_field = Vec2::new(win_size.w, win_size.h) / 2.;
+    for (parent, ghost, mut transform, mut sprite, mut visibility) in ghost_query.iter_mut() {
+        let (parent_tf, size, parent_sprite) = match parent_query.get(parent.get()) {
+            Ok(parent) => parent,
+            Err(_) => continue,
+        };
+        let position = parent_tf.translation.truncate();
+        let near_edge = position.abs() + half_extent(parent_tf, size) - half_field;
+        let crosses = (ghost.0.x == 0. || near_edge.x > 0.) && (ghost.0.y == 0. || near_edge.y > 0.);
+        visibility.is_visible = *mode == BoundaryMode::Wrap && crosses;
+        if !visibility.is_visible {
+            continue;
+        }
+        // world offset to the opposite side, brought into the parent's local space
+        let offset = -position.signum() * ghost.0 * Vec2::new(win_size.w, win_size.h);
+        let local = parent_tf.rotation.inverse() * offset.extend(0.);
+        transform.translation = local / parent_tf.scale;
+        // follow tints put on the original
+        if sprite.color != parent_sprite.color {
+            sprite.color = parent_sprite.color;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{Enemy, FromEnemy, Laser, Player, SpriteSize, Velocity},
+    console::ConsoleAppExt,
+    input::{InputSource, PlayerActions, PlayerInputLabel},
+    WinSize, BASE_SPEED,
+};
+
+// How far ahead (seconds) incoming lasers are considered
+const DODGE_HORIZON: f32 = 1.2;
+// Extra clearance kept around the player's hitbox when dodging
+const DODGE_MARGIN: f32 = 40.;
+// Enemies closer than this push the bot away
+const ENEMY_CLEARANCE: f32 = 220.;
+// Lasers this many seconds from a hit it can't dodge make the bot raise its shield
+const SHIELD_HORIZON: f32 = 0.3;
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(autopilot_toggle_system)
+            .add_system(autopilot_system.label(PlayerInputLabel))
+            .register_console_command("autopilot", "autopilot", autopilot_command);
+    }
+}
+
+fn toggle(source: &mut InputSource) {
+    *source = match *source {
+        InputSource::Keyboard => InputSource::Autopilot,
+        InputSource::Autopilot => InputSource::Keyboard,
+        InputSource::Agent => InputSource::Agent,
+    };
+}
+
+fn autopilot_toggle_system(kb: Res<Input<KeyCode>>, mut source: ResMut<InputSource>) {
+    if kb.just_pressed(KeyCode::F4) {
+        toggle(&mut source);
+    }
+}
+
+fn autopilot_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut source = world.resource_mut::<InputSource>();
+    toggle(&mut source);
+    Ok(format!("input source {:?}", *source))
+}
+
+type EnemyLaserQuery<'w, 's> = Query<'w, 's, (&'static Transform, &'static Velocity), (With<Laser>, With<FromEnemy>)>;
+
+/// Bot driving the player through `PlayerActions`: dodges incoming enemy
+/// lasers, shields against the ones it's too late for, keeps its distance
+/// from enemies and shoots at the nearest one. There are no pickups in the
+/// game yet, so it doesn't go after any.
+fn autopilot_system(
+    source: Res<InputSource>,
+    win_size: Res<WinSize>,
+    mut actions: ResMut<PlayerActions>,
+    player_query: Query<(&Transform, &SpriteSize), With<Player>>,
+    laser_query: EnemyLaserQuery,
+    enemy_query: Query<&Transform, With<Enemy>>,
+) {
+    if *source != InputSource::Autopilot {
+        return;
+    }
+    let (player_tf, player_size) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => {
+            *actions = PlayerActions::default();
+            return;
+        }
+    };
+    let position = player_tf.translation.truncate();
+    let danger_radius = (player_size.0 * player_tf.scale.truncate()).max_element() / 2. + DODGE_MARGIN;
+    let mut steer = Vec2::ZERO;
+    let mut shield = false;
+
+    // dodge lasers whose closest approach comes within the danger radius
+    for (laser_tf, velocity) in laser_query.iter() {
+        let relative = laser_tf.translation.truncate() - position;
+        let laser_velocity = Vec2::new(velocity.x, velocity.y) * BASE_SPEED;
+        let speed_sq = laser_velocity.length_squared();
+        if speed_sq == 0. {
+            continue;
+        }
+        let t = -relative.dot(laser_velocity) / speed_sq;
+        if !(0. ..DODGE_HORIZON).contains(&t) {
+            continue;
+        }
+        let closest = relative + laser_velocity * t;
+        let miss = closest.length();
+        if miss < danger_radius {
+            // sidestep perpendicular to the laser, away from its path
+            let away = if miss > f32::EPSILON {
+                -closest / miss
+            } else {
+                laser_velocity.perp().normalize_or_zero()
+            };
+            let urgency = (1. - t / DODGE_HORIZON) * (1. - miss / danger_radius);
+            steer += away * (1. + 2. * urgency);
+            shield |= t < SHIELD_HORIZON;
+        }
+    }
+
+    // keep clear of enemies, and aim at the nearest one
+    let mut nearest: Option<Vec2> = None;
+    for enemy_tf in enemy_query.iter() {
+        let offset = enemy_tf.translation.truncate() - position;
+        let distance = offset.length();
+        if distance < ENEMY_CLEARANCE && distance > f32::EPSILON {
+            steer -= offset / distance * (1. - distance / ENEMY_CLEARANCE);
+        }
+        if nearest.is_none_or(|n| distance < n.length()) {
+            nearest = Some(offset);
+        }
+    }
+
+    // drift back towards a home spot in the lower part of the field
+    let home = Vec2::new(0., -win_size.h / 4.);
+    steer += (home - position) / (win_size.h / 2.) * 0.5;
+
+    actions.movement = steer.clamp_length_max(1.);
+    actions.aim = nearest.map(|offset| offset.x.atan2(offset.y));
+    actions.shield = shield;
+}
@@ -0,0 +1,228 @@
+/// BulletML numeric expression: numbers, `$rand`, `$rank`, `$1`..`$n`,
+/// `+ - * / %` and parentheses.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Num(f32),
+    Rand,
+    Rank,
+    Param(usize),
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// What an expression is evaluated against.
+pub struct Env<'a> {
+    pub params: &'a [f32],
+    pub rank: f32,
+    pub rand: &'a mut dyn FnMut() -> f32,
+}
+
+impl Expr {
+    pub fn eval(&self, env: &mut Env) -> f32 {
+        match self {
+            Expr::Num(value) => *value,
+            Expr::Rand => (env.rand)(),
+            Expr::Rank => env.rank,
+            // params are 1-based, missing ones read as 0
+            Expr::Param(index) => env.params.get(index.wrapping_sub(1)).copied().unwrap_or(0.),
+            Expr::Neg(expr) => -expr.eval(env),
+            Expr::Bin(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(env), rhs.eval(env));
+                match op {
+                    Op::Add => lhs + rhs,
+                    Op::Sub => lhs - rhs,
+                    Op::Mul => lhs * rhs,
+                    Op::Div if rhs == 0. => 0.,
+                    Op::Div => lhs / rhs,
+                    Op::Rem if rhs == 0. => 0.,
+                    Op::Rem => lhs % rhs,
+                }
+            }
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            source,
+            chars: source.char_indices().peekable(),
+        };
+        let expr = parser.sum()?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            None => Ok(expr),
+            Some(&(at, c)) => Err(format!("unexpected '{}' at {} in '{}'", c, at, source)),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if matches!(self.chars.peek(), Some(&(_, c)) if c == expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else if self.eat('%') {
+                Op::Rem
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        if self.eat('(') {
+            let expr = self.sum()?;
+            if !self.eat(')') {
+                return Err(format!("missing ')' in '{}'", self.source));
+            }
+            return Ok(expr);
+        }
+        self.skip_whitespace();
+        let start = match self.chars.peek() {
+            Some(&(at, _)) => at,
+            None => return Err(format!("unexpected end of '{}'", self.source)),
+        };
+        let mut end = start;
+        while let Some(&(at, c)) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '.' || c == '$' || c == '_') {
+                break;
+            }
+            end = at + c.len_utf8();
+            self.chars.next();
+        }
+        let token = &self.source[start..end];
+        match token {
+            "" => Err(format!("expected a value at {} in '{}'", start, self.source)),
+            "$rand" => Ok(Expr::Rand),
+            "$rank" => Ok(Expr::Rank),
+            _ => {
+                if let Some(index) = token.strip_prefix('$') {
+                    index
+                        .parse()
+                        .map(Expr::Param)
+                        .map_err(|_| format!("unknown variable '{}' in '{}'", token, self.source))
+                } else {
+                    token
+                        .parse()
+                        .map(Expr::Num)
+                        .map_err(|_| format!("bad number '{}' in '{}'", token, self.source))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, params: &[f32]) -> f32 {
+        let mut rand = || 0.25;
+        Expr::parse(source).unwrap().eval(&mut Env {
+            params,
+            rank: 0.5,
+            rand: &mut rand,
+        })
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.);
+        assert_eq!(eval("12 / 2 / 3", &[]), 2.);
+        assert_eq!(eval("7 % 4 * 2", &[]), 6.);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(eval("-2 * 3", &[]), -6.);
+        assert_eq!(eval("2 * -3", &[]), -6.);
+        assert_eq!(eval("--2", &[]), 2.);
+        assert_eq!(eval("-(1 + 2)", &[]), -3.);
+        assert_eq!(eval("1 - -1", &[]), 2.);
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(eval("$1 + $2 * 10", &[1., 2.]), 21.);
+        assert_eq!(eval("$3", &[1., 2.]), 0.);
+        assert_eq!(eval("$0", &[1., 2.]), 0.);
+        assert_eq!(eval("$rank * 2", &[]), 1.);
+        assert_eq!(eval("$rand * 4", &[]), 1.);
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        assert_eq!(eval("1 / 0", &[]), 0.);
+        assert_eq!(eval("1 % 0", &[]), 0.);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| Expr::parse(source).unwrap_err();
+        assert_eq!(error("1 +"), "unexpected end of '1 +'");
+        assert_eq!(error("(1 + 2"), "missing ')' in '(1 + 2'");
+        assert_eq!(error("1 2"), "unexpected '2' at 2 in '1 2'");
+        assert_eq!(error("*2"), "expected a value at 0 in '*2'");
+        assert_eq!(error("$foo"), "unknown variable '$foo' in '$foo'");
+        assert_eq!(error("1.2.3"), "bad number '1.2.3' in '1.2.3'");
+    }
+}
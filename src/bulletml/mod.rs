@@ -0,0 +1,235 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+
+use crate::{
+    components::{Damage, Despawn, ParentEntity, Player, Velocity},
+    events::ProjectileFired,
+    projectile::{self, ProjectileDef, Shooter},
+    tuning::Tuning,
+    GameRng, GameTextures, GameTime, BASE_SPEED, TIME_STEP,
+};
+
+pub use self::parse::BulletMl;
+pub use self::runner::{Context, Runner, Shot};
+
+mod expr;
+mod parse;
+mod runner;
+
+/// BulletML speeds are pixels per 60 Hz frame, `Velocity` is in `BASE_SPEED`s.
+const SPEED_UNIT: f32 = 1. / TIME_STEP / BASE_SPEED;
+
+/// BulletML (https://www.asahi-net.or.jp/~cs8k-cyu/bulletml/) bullet patterns
+/// loaded as `.xml` assets, reloaded whenever the file changes on disk.
+pub struct BulletMlPlugin;
+
+impl Plugin for BulletMlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<BulletMlAsset>()
+            .init_asset_loader::<BulletMlLoader>()
+            .insert_resource(BulletMlLibrary::default())
+            .add_system(bulletml_reload_system)
+            .add_system(bulletml_bullet_system);
+    }
+}
+
+#[derive(TypeUuid)]
+#[uuid = "5b1b7e0c-3c84-4f57-9a55-2d0b8f0e6c41"]
+pub struct BulletMlAsset(pub Arc<BulletMl>);
+
+#[derive(Default)]
+struct BulletMlLoader;
+
+impl AssetLoader for BulletMlLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let source = std::str::from_utf8(bytes)?;
+            let bulletml = parse::parse(source)
+                .map_err(|err| bevy::asset::Error::msg(format!("{}: {}", load_context.path().display(), err)))?;
+            load_context.set_default_asset(LoadedAsset::new(BulletMlAsset(Arc::new(bulletml))));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xml"]
+    }
+}
+
+/// Handles of the BulletML files in use, keyed by their path under `assets/`.
+#[derive(Default)]
+pub struct BulletMlLibrary {
+    handles: HashMap<&'static str, Handle<BulletMlAsset>>,
+}
+
+/// Loads BulletML documents on first use and hands out the parsed result.
+#[derive(SystemParam)]
+pub struct BulletMlDocs<'w, 's> {
+    library: ResMut<'w, BulletMlLibrary>,
+    assets: Res<'w, Assets<BulletMlAsset>>,
+    asset_server: Res<'w, AssetServer>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s usize>,
+}
+
+impl<'w, 's> BulletMlDocs<'w, 's> {
+    /// The document at `path`, `None` until it has loaded (or if it failed to).
+    pub fn get(&mut self, path: &'static str) -> Option<Arc<BulletMl>> {
+        let asset_server = &self.asset_server;
+        let handle = self
+            .library
+            .handles
+            .entry(path)
+            .or_insert_with(|| asset_server.load(path));
+        self.assets.get(handle).map(|asset| asset.0.clone())
+    }
+}
+
+/// Fires an enemy's bullets from a BulletML document instead of a `FirePattern`.
+#[derive(Component)]
+pub struct BulletMlEmitter {
+    pub path: &'static str,
+    /// Started once the document has loaded, cleared to restart on reload.
+    pub runner: Option<Runner>,
+}
+
+impl BulletMlEmitter {
+    pub fn new(path: &'static str) -> Self {
+        Self { path, runner: None }
+    }
+}
+
+/// A bullet still following actions from its BulletML document.
+#[derive(Component)]
+pub struct BulletMlBullet {
+    runner: Runner,
+    def: ProjectileDef,
+}
+
+/// The `rand` BulletML expressions draw from.
+pub fn rand(rng: &mut GameRng) -> impl FnMut() -> f32 + '_ {
+    use rand::Rng;
+    move || rng.0.gen::<f32>()
+}
+
+/// Spawn `shots` as projectiles of `def`, the ones with actions of their own
+/// keep being driven by their runner. Returns the projectiles spawned.
+pub fn spawn_shots(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    def: &ProjectileDef,
+    shooter: Shooter,
+    position: Vec3,
+    shots: Vec<Shot>,
+    damage: Damage,
+) -> Vec<Entity> {
+    let mut spawned = Vec::new();
+    for shot in shots {
+        let radians = shot.direction.to_radians();
+        let direction = Vec2::new(radians.sin(), radians.cos());
+        let scaled = ProjectileDef {
+            speed: def.speed * shot.speed * SPEED_UNIT,
+            ..*def
+        };
+        let entities = projectile::fire(commands, game_textures, &scaled, shooter, position, direction, damage);
+        if let Some(runner) = shot.runner {
+            for &entity in &entities {
+                commands.entity(entity).insert(BulletMlBullet {
+                    runner: runner.clone(),
+                    def: *def,
+                });
+            }
+        }
+        spawned.extend(entities);
+    }
+    spawned
+}
+
+fn bulletml_reload_system(
+    mut asset_events: EventReader<AssetEvent<BulletMlAsset>>,
+    library: Res<BulletMlLibrary>,
+    mut emitters: Query<&mut BulletMlEmitter>,
+) {
+    for event in asset_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            let reloaded: Vec<&'static str> = library
+                .handles
+                .iter()
+                .filter(|(_, loaded)| *loaded == handle)
+                .map(|(path, _)| *path)
+                .collect();
+            for mut emitter in emitters.iter_mut() {
+                if reloaded.contains(&emitter.path) {
+                    info!("reloaded bullet pattern {}", emitter.path);
+                    emitter.runner = None;
+                }
+            }
+        }
+    }
+}
+
+type BulletMlBulletQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        &'static mut Velocity,
+        &'static mut BulletMlBullet,
+        &'static Damage,
+        Option<&'static ParentEntity>,
+    ),
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn bulletml_bullet_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    game_textures: Res<GameTextures>,
+    tuning: Res<Tuning>,
+    mut rng: ResMut<GameRng>,
+    mut query: BulletMlBulletQuery,
+    player_query: Query<&Transform, (With<Player>, Without<BulletMlBullet>)>,
+    mut fired_events: EventWriter<ProjectileFired>,
+) {
+    let target = player_query.get_single().ok().map(|tf| tf.translation.truncate());
+    let mut rand = rand(&mut rng);
+    for (entity, mut transform, mut velocity, mut bullet, damage, parent) in query.iter_mut() {
+        let mut ctx = Context {
+            position: transform.translation.truncate(),
+            target,
+            rank: tuning.bulletml_rank,
+            rand: &mut rand,
+        };
+        let shots = bullet.runner.update(game_time.delta_seconds(), &mut ctx);
+        let shooter = Shooter::Enemy(parent.map_or(entity, |parent| parent.entity));
+        let fired = spawn_shots(&mut commands, &game_textures, &bullet.def, shooter, transform.translation, shots, *damage);
+        for _ in fired {
+            fired_events.send(ProjectileFired {
+                from_player: false,
+                position: transform.translation,
+            });
+        }
+
+        if bullet.runner.vanished {
+            commands.entity(entity).insert(Despawn);
+            continue;
+        }
+        let moved = bullet.runner.velocity() * bullet.def.speed * SPEED_UNIT;
+        (velocity.x, velocity.y) = (moved.x, moved.y);
+        if moved != Vec2::ZERO {
+            transform.rotation = Quat::from_rotation_z((-moved.x).atan2(moved.y));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use bevy::utils::HashMap;
+use roxmltree::{Document, Node};
+
+use super::expr::Expr;
+
+/// A parsed BulletML document.
+#[derive(Debug, Default)]
+pub struct BulletMl {
+    /// `type="horizontal"` documents measure directions from the right
+    /// instead of from the top.
+    pub horizontal: bool,
+    pub actions: HashMap<String, Arc<Action>>,
+    pub bullets: HashMap<String, Arc<Bullet>>,
+    pub fires: HashMap<String, Arc<Fire>>,
+}
+
+impl BulletMl {
+    /// The actions run by the emitter: every labelled action starting with "top".
+    pub fn top_actions(&self) -> Vec<Arc<Action>> {
+        let mut labels: Vec<&String> = self.actions.keys().filter(|label| label.starts_with("top")).collect();
+        labels.sort();
+        labels.into_iter().map(|label| self.actions[label].clone()).collect()
+    }
+}
+
+/// Either an inline element or a `*Ref` to a labelled one, with its params.
+#[derive(Debug)]
+pub enum Ref<T> {
+    Inline(Arc<T>),
+    Label(String, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirectionType {
+    Aim,
+    Absolute,
+    Relative,
+    Sequence,
+}
+
+#[derive(Debug)]
+pub struct Direction {
+    pub kind: DirectionType,
+    pub degrees: Expr,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpeedType {
+    Absolute,
+    Relative,
+    Sequence,
+}
+
+#[derive(Debug)]
+pub struct Speed {
+    pub kind: SpeedType,
+    pub value: Expr,
+}
+
+#[derive(Debug, Default)]
+pub struct Action {
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug)]
+pub struct Bullet {
+    pub direction: Option<Direction>,
+    pub speed: Option<Speed>,
+    pub actions: Vec<Ref<Action>>,
+}
+
+#[derive(Debug)]
+pub struct Fire {
+    pub direction: Option<Direction>,
+    pub speed: Option<Speed>,
+    pub bullet: Ref<Bullet>,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Repeat { times: Expr, action: Ref<Action> },
+    Fire(Ref<Fire>),
+    ChangeSpeed { speed: Speed, term: Expr },
+    ChangeDirection { direction: Direction, term: Expr },
+    Accel {
+        horizontal: Option<Speed>,
+        vertical: Option<Speed>,
+        term: Expr,
+    },
+    Wait(Expr),
+    Vanish,
+    Action(Ref<Action>),
+}
+
+pub fn parse(source: &str) -> Result<BulletMl, String> {
+    let document = Document::parse(source).map_err(|err| err.to_string())?;
+    let root = document.root_element();
+    if root.tag_name().name() != "bulletml" {
+        return Err(format!("expected <bulletml>, found <{}>", root.tag_name().name()));
+    }
+    let mut bulletml = BulletMl {
+        horizontal: root.attribute("type") == Some("horizontal"),
+        ..Default::default()
+    };
+    for node in elements(root) {
+        let label = match node.attribute("label") {
+            Some(label) => label.to_string(),
+            None => continue,
+        };
+        match node.tag_name().name() {
+            "action" => {
+                bulletml.actions.insert(label, Arc::new(action(node)?));
+            }
+            "bullet" => {
+                bulletml.bullets.insert(label, Arc::new(bullet(node)?));
+            }
+            "fire" => {
+                bulletml.fires.insert(label, Arc::new(fire(node)?));
+            }
+            other => return Err(format!("unexpected <{}> in <bulletml>", other)),
+        }
+    }
+    if bulletml.top_actions().is_empty() {
+        return Err("no top action".to_string());
+    }
+    Ok(bulletml)
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(|child| child.is_element())
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    elements(node).find(|child| child.tag_name().name() == name)
+}
+
+fn required_child<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Result<Node<'a, 'input>, String> {
+    elements(node)
+        .find(|child| names.contains(&child.tag_name().name()))
+        .ok_or_else(|| format!("<{}> needs a <{}>", node.tag_name().name(), names.join("> or <")))
+}
+
+fn expr(node: Node) -> Result<Expr, String> {
+    Expr::parse(node.text().unwrap_or("").trim())
+}
+
+fn required_expr(node: Node, name: &str) -> Result<Expr, String> {
+    expr(required_child(node, &[name])?)
+}
+
+fn params(node: Node) -> Result<Vec<Expr>, String> {
+    elements(node)
+        .filter(|child| child.tag_name().name() == "param")
+        .map(expr)
+        .collect()
+}
+
+fn label(node: Node) -> Result<String, String> {
+    node.attribute("label")
+        .map(str::to_string)
+        .ok_or_else(|| format!("<{}> needs a label", node.tag_name().name()))
+}
+
+fn direction(node: Node) -> Result<Direction, String> {
+    let kind = match node.attribute("type").unwrap_or("aim") {
+        "aim" => DirectionType::Aim,
+        "absolute" => DirectionType::Absolute,
+        "relative" => DirectionType::Relative,
+        "sequence" => DirectionType::Sequence,
+        other => return Err(format!("unknown direction type '{}'", other)),
+    };
+    Ok(Direction {
+        kind,
+        degrees: expr(node)?,
+    })
+}
+
+fn speed(node: Node) -> Result<Speed, String> {
+    let kind = match node.attribute("type").unwrap_or("absolute") {
+        "absolute" => SpeedType::Absolute,
+        "relative" => SpeedType::Relative,
+        "sequence" => SpeedType::Sequence,
+        other => return Err(format!("unknown speed type '{}'", other)),
+    };
+    Ok(Speed {
+        kind,
+        value: expr(node)?,
+    })
+}
+
+fn optional<T>(node: Node, name: &str, parse: fn(Node) -> Result<T, String>) -> Result<Option<T>, String> {
+    child(node, name).map(parse).transpose()
+}
+
+fn action_ref(node: Node) -> Result<Ref<Action>, String> {
+    match node.tag_name().name() {
+        "action" => Ok(Ref::Inline(Arc::new(action(node)?))),
+        _ => Ok(Ref::Label(label(node)?, params(node)?)),
+    }
+}
+
+fn action(node: Node) -> Result<Action, String> {
+    let mut commands = Vec::new();
+    for node in elements(node) {
+        let command = match node.tag_name().name() {
+            "repeat" => Command::Repeat {
+                times: required_expr(node, "times")?,
+                action: action_ref(required_child(node, &["action", "actionRef"])?)?,
+            },
+            "fire" => Command::Fire(Ref::Inline(Arc::new(fire(node)?))),
+            "fireRef" => Command::Fire(Ref::Label(label(node)?, params(node)?)),
+            "changeSpeed" => Command::ChangeSpeed {
+                speed: speed(required_child(node, &["speed"])?)?,
+                term: required_expr(node, "term")?,
+            },
+            "changeDirection" => Command::ChangeDirection {
+                direction: direction(required_child(node, &["direction"])?)?,
+                term: required_expr(node, "term")?,
+            },
+            "accel" => Command::Accel {
+                horizontal: optional(node, "horizontal", speed)?,
+                vertical: optional(node, "vertical", speed)?,
+                term: required_expr(node, "term")?,
+            },
+            "wait" => Command::Wait(expr(node)?),
+            "vanish" => Command::Vanish,
+            "action" | "actionRef" => Command::Action(action_ref(node)?),
+            other => return Err(format!("unexpected <{}> in <action>", other)),
+        };
+        commands.push(command);
+    }
+    Ok(Action { commands })
+}
+
+fn bullet(node: Node) -> Result<Bullet, String> {
+    Ok(Bullet {
+        direction: optional(node, "direction", direction)?,
+        speed: optional(node, "speed", speed)?,
+        actions: elements(node)
+            .filter(|child| matches!(child.tag_name().name(), "action" | "actionRef"))
+            .map(action_ref)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn fire(node: Node) -> Result<Fire, String> {
+    let bullet_node = required_child(node, &["bullet", "bulletRef"])?;
+    let bullet = match bullet_node.tag_name().name() {
+        "bullet" => Ref::Inline(Arc::new(bullet(bullet_node)?)),
+        _ => Ref::Label(label(bullet_node)?, params(bullet_node)?),
+    };
+    Ok(Fire {
+        direction: optional(node, "direction", direction)?,
+        speed: optional(node, "speed", speed)?,
+        bullet,
+    })
+}
@@ -0,0 +1,457 @@
+use std::sync::Arc;
+
+use bevy::{math::Vec2, utils::HashMap};
+
+use crate::TIME_STEP;
+
+use super::{
+    expr::{Env, Expr},
+    parse::{Action, BulletMl, Command, Direction, DirectionType, Ref, Speed, SpeedType},
+};
+
+// Guards against actionRef cycles and zero-wait loops locking up a frame
+const MAX_COMMANDS_PER_FRAME: u32 = 10_000;
+
+/// What the runner sees of the world this frame.
+pub struct Context<'a> {
+    pub position: Vec2,
+    pub target: Option<Vec2>,
+    pub rank: f32,
+    pub rand: &'a mut dyn FnMut() -> f32,
+}
+
+/// A bullet fired by a runner: direction in degrees (0 up, clockwise), speed
+/// in BulletML pixels per frame, and its own runner if the bullet has actions.
+pub struct Shot {
+    pub direction: f32,
+    pub speed: f32,
+    pub runner: Option<Runner>,
+}
+
+#[derive(Clone)]
+struct Frame {
+    action: Arc<Action>,
+    index: usize,
+    params: Vec<f32>,
+    repeat: u32,
+}
+
+/// Per-frame change in progress from changeSpeed/changeDirection/accel.
+#[derive(Clone, Copy)]
+struct Change<T> {
+    per_frame: T,
+    frames: u32,
+}
+
+/// Runs BulletML actions for one mover (an emitter or a bullet), one 60 Hz
+/// frame at a time.
+#[derive(Clone)]
+pub struct Runner {
+    doc: Arc<BulletMl>,
+    stack: Vec<Frame>,
+    wait: u32,
+    clock: f32,
+    /// Own heading in degrees, only moves bullets.
+    pub direction: f32,
+    /// Own speed in pixels per frame, only moves bullets.
+    pub speed: f32,
+    /// Extra velocity from accel, x right and y down as BulletML has it.
+    accel: Vec2,
+    last_direction: f32,
+    last_speed: f32,
+    speed_change: Option<Change<f32>>,
+    direction_change: Option<Change<f32>>,
+    accel_change: Option<Change<Vec2>>,
+    pub vanished: bool,
+}
+
+impl Runner {
+    /// Runner for an emitter, running the document's top actions.
+    pub fn top(doc: Arc<BulletMl>) -> Self {
+        let actions = doc.top_actions().into_iter().map(|action| (action, Vec::new())).collect();
+        // emitters face down the screen
+        Self::new(doc, actions, 180., 0.)
+    }
+
+    fn new(doc: Arc<BulletMl>, actions: Vec<(Arc<Action>, Vec<f32>)>, direction: f32, speed: f32) -> Self {
+        Self {
+            doc,
+            // the stack runs from the back, so the first action goes on top
+            stack: actions
+                .into_iter()
+                .rev()
+                .map(|(action, params)| Frame {
+                    action,
+                    index: 0,
+                    params,
+                    repeat: 1,
+                })
+                .collect(),
+            wait: 0,
+            clock: 0.,
+            direction,
+            speed,
+            accel: Vec2::ZERO,
+            last_direction: direction,
+            last_speed: speed,
+            speed_change: None,
+            direction_change: None,
+            accel_change: None,
+            vanished: false,
+        }
+    }
+
+    /// Velocity in pixels per frame, y up.
+    pub fn velocity(&self) -> Vec2 {
+        let radians = self.direction.to_radians();
+        Vec2::new(radians.sin(), radians.cos()) * self.speed + Vec2::new(self.accel.x, -self.accel.y)
+    }
+
+    /// Advance by `seconds` of game time and return the shots fired meanwhile.
+    pub fn update(&mut self, seconds: f32, ctx: &mut Context) -> Vec<Shot> {
+        let mut shots = Vec::new();
+        self.clock += seconds;
+        while self.clock >= TIME_STEP {
+            self.clock -= TIME_STEP;
+            self.step(ctx, &mut shots);
+        }
+        shots
+    }
+
+    fn step(&mut self, ctx: &mut Context, shots: &mut Vec<Shot>) {
+        if self.vanished {
+            return;
+        }
+        self.apply_changes();
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+        let mut budget = MAX_COMMANDS_PER_FRAME;
+        while let Some(frame) = self.stack.last_mut() {
+            budget -= 1;
+            if budget == 0 {
+                break;
+            }
+            if frame.index >= frame.action.commands.len() {
+                if frame.repeat > 1 {
+                    frame.repeat -= 1;
+                    frame.index = 0;
+                } else {
+                    self.stack.pop();
+                }
+                continue;
+            }
+            let action = frame.action.clone();
+            let params = frame.params.clone();
+            let command = &action.commands[frame.index];
+            frame.index += 1;
+
+            match command {
+                Command::Repeat { times, action } => {
+                    let times = eval(times, &params, ctx).floor();
+                    if times >= 1. {
+                        if let Some((action, params)) = resolve(&self.doc.actions, action, &params, ctx) {
+                            self.stack.push(Frame {
+                                action,
+                                index: 0,
+                                params,
+                                repeat: times as u32,
+                            });
+                        }
+                    }
+                }
+                Command::Action(action) => {
+                    if let Some((action, params)) = resolve(&self.doc.actions, action, &params, ctx) {
+                        self.stack.push(Frame {
+                            action,
+                            index: 0,
+                            params,
+                            repeat: 1,
+                        });
+                    }
+                }
+                Command::Fire(fire) => {
+                    if let Some(shot) = self.fire(fire, &params, ctx) {
+                        shots.push(shot);
+                    }
+                }
+                Command::ChangeSpeed { speed, term } => {
+                    let frames = eval(term, &params, ctx).max(1.) as u32;
+                    let value = eval(&speed.value, &params, ctx);
+                    let per_frame = match speed.kind {
+                        SpeedType::Absolute => (value - self.speed) / frames as f32,
+                        SpeedType::Relative => value / frames as f32,
+                        SpeedType::Sequence => value,
+                    };
+                    self.speed_change = Some(Change { per_frame, frames });
+                }
+                Command::ChangeDirection { direction, term } => {
+                    let frames = eval(term, &params, ctx).max(1.) as u32;
+                    let per_frame = match direction.kind {
+                        DirectionType::Sequence => eval(&direction.degrees, &params, ctx),
+                        _ => {
+                            let target = self.direction_of(direction, &params, ctx);
+                            wrap_degrees(target - self.direction) / frames as f32
+                        }
+                    };
+                    self.direction_change = Some(Change { per_frame, frames });
+                }
+                Command::Accel { horizontal, vertical, term } => {
+                    let frames = eval(term, &params, ctx).max(1.) as u32;
+                    let mut axis = |speed: &Option<Speed>, current: f32| match speed {
+                        None => 0.,
+                        Some(speed) => {
+                            let value = eval(&speed.value, &params, ctx);
+                            match speed.kind {
+                                SpeedType::Absolute => (value - current) / frames as f32,
+                                SpeedType::Relative => value / frames as f32,
+                                SpeedType::Sequence => value,
+                            }
+                        }
+                    };
+                    let per_frame = Vec2::new(axis(horizontal, self.accel.x), axis(vertical, self.accel.y));
+                    self.accel_change = Some(Change { per_frame, frames });
+                }
+                Command::Wait(frames) => {
+                    let frames = eval(frames, &params, ctx).max(0.) as u32;
+                    if frames > 0 {
+                        // this frame counts as the first one waited
+                        self.wait = frames - 1;
+                        break;
+                    }
+                }
+                Command::Vanish => {
+                    self.vanished = true;
+                    self.stack.clear();
+                }
+            }
+        }
+    }
+
+    fn apply_changes(&mut self) {
+        if let Some(change) = &mut self.speed_change {
+            self.speed += change.per_frame;
+            change.frames -= 1;
+            if change.frames == 0 {
+                self.speed_change = None;
+            }
+        }
+        if let Some(change) = &mut self.direction_change {
+            self.direction = (self.direction + change.per_frame) % 360.;
+            change.frames -= 1;
+            if change.frames == 0 {
+                self.direction_change = None;
+            }
+        }
+        if let Some(change) = &mut self.accel_change {
+            self.accel += change.per_frame;
+            change.frames -= 1;
+            if change.frames == 0 {
+                self.accel_change = None;
+            }
+        }
+    }
+
+    fn aim(&self, ctx: &Context) -> f32 {
+        match ctx.target {
+            Some(target) if target != ctx.position => {
+                let offset = target - ctx.position;
+                offset.x.atan2(offset.y).to_degrees()
+            }
+            _ => self.direction,
+        }
+    }
+
+    fn direction_of(&self, direction: &Direction, params: &[f32], ctx: &mut Context) -> f32 {
+        let degrees = eval(&direction.degrees, params, ctx);
+        match direction.kind {
+            DirectionType::Aim => self.aim(ctx) + degrees,
+            // horizontal documents count from the right
+            DirectionType::Absolute if self.doc.horizontal => degrees + 90.,
+            DirectionType::Absolute => degrees,
+            DirectionType::Relative => self.direction + degrees,
+            DirectionType::Sequence => self.last_direction + degrees,
+        }
+    }
+
+    fn fire(&mut self, fire: &Ref<super::parse::Fire>, params: &[f32], ctx: &mut Context) -> Option<Shot> {
+        let doc = self.doc.clone();
+        let (fire, fire_params) = resolve(&doc.fires, fire, params, ctx)?;
+        let (bullet, bullet_params) = resolve(&doc.bullets, &fire.bullet, &fire_params, ctx)?;
+
+        let direction = match (&fire.direction, &bullet.direction) {
+            (Some(direction), _) => self.direction_of(direction, &fire_params, ctx),
+            (None, Some(direction)) => self.direction_of(direction, &bullet_params, ctx),
+            (None, None) => self.aim(ctx),
+        };
+        let speed = match (&fire.speed, &bullet.speed) {
+            (Some(speed), _) => self.speed_of(speed, &fire_params, ctx),
+            (None, Some(speed)) => self.speed_of(speed, &bullet_params, ctx),
+            (None, None) => 1.,
+        };
+        self.last_direction = direction;
+        self.last_speed = speed;
+
+        let actions: Vec<_> = bullet
+            .actions
+            .iter()
+            .filter_map(|action| resolve(&doc.actions, action, &bullet_params, ctx))
+            .collect();
+        let runner = if actions.is_empty() {
+            None
+        } else {
+            Some(Runner::new(doc, actions, direction, speed))
+        };
+        Some(Shot {
+            direction,
+            speed,
+            runner,
+        })
+    }
+
+    fn speed_of(&self, speed: &Speed, params: &[f32], ctx: &mut Context) -> f32 {
+        let value = eval(&speed.value, params, ctx);
+        match speed.kind {
+            SpeedType::Absolute => value,
+            SpeedType::Relative => self.speed + value,
+            SpeedType::Sequence => self.last_speed + value,
+        }
+    }
+}
+
+fn eval(expr: &Expr, params: &[f32], ctx: &mut Context) -> f32 {
+    expr.eval(&mut Env {
+        params,
+        rank: ctx.rank,
+        rand: &mut *ctx.rand,
+    })
+}
+
+/// Look up a reference, evaluating a `*Ref`'s params in the caller's scope;
+/// inline elements share the caller's params.
+fn resolve<T>(
+    labelled: &HashMap<String, Arc<T>>,
+    reference: &Ref<T>,
+    params: &[f32],
+    ctx: &mut Context,
+) -> Option<(Arc<T>, Vec<f32>)> {
+    match reference {
+        Ref::Inline(inline) => Some((inline.clone(), params.to_vec())),
+        Ref::Label(label, args) => {
+            let target = labelled.get(label)?.clone();
+            let args = args.iter().map(|arg| eval(arg, params, ctx)).collect();
+            Some((target, args))
+        }
+    }
+}
+
+/// Shortest signed turn, in (-180, 180].
+fn wrap_degrees(degrees: f32) -> f32 {
+    let wrapped = degrees.rem_euclid(360.);
+    if wrapped > 180. {
+        wrapped - 360.
+    } else {
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulletml::parse::parse;
+
+    fn runner(source: &str) -> Runner {
+        Runner::top(Arc::new(parse(source).unwrap()))
+    }
+
+    /// Shots fired on each of the first `frames` frames.
+    fn shots_per_frame(runner: &mut Runner, frames: usize) -> Vec<usize> {
+        let mut rand = || 0.5;
+        let mut ctx = Context {
+            position: Vec2::ZERO,
+            target: None,
+            rank: 0.,
+            rand: &mut rand,
+        };
+        (0..frames).map(|_| runner.update(TIME_STEP, &mut ctx).len()).collect()
+    }
+
+    #[test]
+    fn wrap_degrees_takes_the_short_way() {
+        assert_eq!(wrap_degrees(0.), 0.);
+        assert_eq!(wrap_degrees(90.), 90.);
+        assert_eq!(wrap_degrees(-90.), -90.);
+        assert_eq!(wrap_degrees(180.), 180.);
+        assert_eq!(wrap_degrees(-180.), 180.);
+        assert_eq!(wrap_degrees(190.), -170.);
+        assert_eq!(wrap_degrees(359.), -1.);
+        assert_eq!(wrap_degrees(540.), 180.);
+        assert_eq!(wrap_degrees(-720.), 0.);
+    }
+
+    #[test]
+    fn repeat_and_wait_count_frames() {
+        let mut runner = runner(
+            r#"<bulletml>
+                <action label="top">
+                    <repeat><times>3</times><action>
+                        <fire><bullet/></fire>
+                        <wait>5</wait>
+                    </action></repeat>
+                </action>
+            </bulletml>"#,
+        );
+        let shots = shots_per_frame(&mut runner, 20);
+        let fired: Vec<usize> = (0..shots.len()).filter(|&frame| shots[frame] > 0).collect();
+        assert_eq!(fired, vec![0, 5, 10]);
+        assert_eq!(shots.iter().sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn zero_wait_stays_in_the_frame() {
+        let mut runner = runner(
+            r#"<bulletml>
+                <action label="top">
+                    <repeat><times>4</times><action>
+                        <fire><bullet/></fire>
+                        <wait>0</wait>
+                    </action></repeat>
+                </action>
+            </bulletml>"#,
+        );
+        assert_eq!(shots_per_frame(&mut runner, 3), vec![4, 0, 0]);
+    }
+
+    #[test]
+    fn action_ref_params() {
+        let mut runner = runner(
+            r#"<bulletml>
+                <action label="top">
+                    <actionRef label="burst"><param>1 + 1</param></actionRef>
+                </action>
+                <action label="burst">
+                    <repeat><times>$1</times><action><fire><bullet/></fire></action></repeat>
+                </action>
+            </bulletml>"#,
+        );
+        assert_eq!(shots_per_frame(&mut runner, 2), vec![2, 0]);
+    }
+
+    #[test]
+    fn action_ref_cycle_stops_at_the_frame_budget() {
+        let mut runner = runner(
+            r#"<bulletml>
+                <action label="top">
+                    <fire><bullet/></fire>
+                    <actionRef label="top"/>
+                </action>
+            </bulletml>"#,
+        );
+        // one fire and one actionRef per shot, and the next frame carries on
+        let most = MAX_COMMANDS_PER_FRAME as usize / 2;
+        for shots in shots_per_frame(&mut runner, 2) {
+            assert!(shots > most - 2 && shots <= most, "{} shots in a frame", shots);
+        }
+    }
+}
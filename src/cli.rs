@@ -0,0 +1,89 @@
+use std::{env, process, str::FromStr};
+
+use crate::{agent::AgentTransport, tuning::Tuning};
+
+const USAGE: &str = "\
+usage: rust_invaders [--agent stdio|tcp:<addr>] [--headless] [--seed <n>] [--difficulty easy|normal|hard] [--boundary clamp|bounce|wrap] [--set <key>=<value>]...
+       rust_invaders simulate [--runs <n>] [--max-time <secs>] [--out <file.csv>] [--seed <n>] [--difficulty easy|normal|hard] [--boundary clamp|bounce|wrap] [--set <key>=<value>]...";
+
+/// Command line options.
+pub struct Args {
+    /// Run the batch balancing simulator instead of the game.
+    pub simulate: bool,
+    pub agent: Option<AgentTransport>,
+    pub headless: bool,
+    pub seed: Option<u64>,
+    pub tuning: Tuning,
+    pub runs: u32,
+    pub max_time: f64,
+    pub out: String,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            simulate: false,
+            agent: None,
+            headless: false,
+            seed: None,
+            tuning: Tuning::default(),
+            runs: 10,
+            max_time: 600.,
+            out: "simulation.csv".to_string(),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Args::default();
+        let mut iter = env::args().skip(1).peekable();
+        if iter.peek().map(String::as_str) == Some("simulate") {
+            iter.next();
+            args.simulate = true;
+        }
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--agent" if !args.simulate => {
+                    args.agent = match iter.next().as_deref() {
+                        Some("stdio") => Some(AgentTransport::Stdio),
+                        Some(value) if value.starts_with("tcp:") => {
+                            Some(AgentTransport::Tcp(value["tcp:".len()..].to_string()))
+                        }
+                        _ => exit_with_usage("--agent expects stdio or tcp:<addr>"),
+                    }
+                }
+                "--headless" => args.headless = true,
+                "--seed" => args.seed = Some(value(&arg, iter.next())),
+                "--difficulty" => args.tuning.difficulty = value(&arg, iter.next()),
+                "--boundary" => args.tuning.boundary = value(&arg, iter.next()),
+                "--set" => {
+                    let assignment: String = value(&arg, iter.next());
+                    if let Err(err) = args.tuning.apply(&assignment) {
+                        exit_with_usage(&err);
+                    }
+                }
+                "--runs" if args.simulate => args.runs = value(&arg, iter.next()),
+                "--max-time" if args.simulate => args.max_time = value(&arg, iter.next()),
+                "--out" if args.simulate => args.out = value(&arg, iter.next()),
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                other => exit_with_usage(&format!("unknown argument '{}'", other)),
+            }
+        }
+        args
+    }
+}
+
+fn value<T: FromStr>(flag: &str, value: Option<String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| exit_with_usage(&format!("{} expects a value", flag)))
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
@@ -1,9 +1,13 @@
+use std::str::FromStr;
+
 use bevy::{
     time::Timer,
     math::{Vec2, Vec3},
     prelude::{Component, Entity},
 };
 
+use crate::events::{DamageEvent, DamageSource};
+
 // region: --- Common Components
 #[derive(Component,Clone, Copy)]
 pub struct Velocity {
@@ -19,31 +23,138 @@ pub struct Movable {
 #[derive(Component)]
 pub struct Laser;
 
+/// Despawn request, processed once at the end of the frame.
+#[derive(Component)]
+pub struct Despawn;
+
 #[derive(Component)]
 pub struct ScoreText;
 
+/// Hit points, for the player and enemies alike. Damage taken is scaled by
+/// `multiplier`: 0.5 takes half, 0 is immune.
 #[derive(Component)]
 pub struct Health {
     pub hp: f32,
     pub multiplier: f32,
 }
 
+/// Flat reduction of every hit, taken off after crits and resistances and
+/// before `Health.multiplier`. Acid corrosion lowers it for a while.
+#[derive(Component)]
+pub struct Armour(pub f32);
+
+/// Damage is ignored while `timer` runs. Every hit that lands restarts it
+/// at `after_hit` seconds.
+#[derive(Component)]
+pub struct Immunity {
+    pub timer: Timer,
+    pub after_hit: f32,
+}
+
+impl Immunity {
+    /// Immune for `initial` seconds from now.
+    pub fn new(initial: f32, after_hit: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(initial, false),
+            after_hit,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    pub fn hit(&mut self) {
+        self.timer = Timer::from_seconds(self.after_hit, false);
+    }
+}
+
 #[derive(Component)]
 pub struct NumberOfHits {
     pub hits: i32
 }
 
-#[derive(Component)]
+/// Element of a hit. Anything but kinetic leaves a status effect behind,
+/// see `StatusEffects`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DamageKind {
+    #[default]
+    Kinetic,
+    /// Sets the target burning.
+    Fire,
+    /// Slows the target, and freezes its guns when hits pile up.
+    Ice,
+    /// Eats away at the target's armour.
+    Acid,
+}
+
+impl FromStr for DamageKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kinetic" => Ok(DamageKind::Kinetic),
+            "fire" => Ok(DamageKind::Fire),
+            "ice" => Ok(DamageKind::Ice),
+            "acid" => Ok(DamageKind::Acid),
+            _ => Err(format!("unknown damage kind '{}'", s)),
+        }
+    }
+}
+
+/// Multipliers on damage taken per `DamageKind`: 1 is normal, below 1
+/// resists, 0 is immune to the hit and its status effect, above 1 is weak.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub fire: f32,
+    pub ice: f32,
+    pub acid: f32,
+}
+
+impl Resistances {
+    pub const NONE: Resistances = Resistances {
+        kinetic: 1.,
+        fire: 1.,
+        ice: 1.,
+        acid: 1.,
+    };
+
+    pub fn against(&self, kind: DamageKind) -> f32 {
+        match kind {
+            DamageKind::Kinetic => self.kinetic,
+            DamageKind::Fire => self.fire,
+            DamageKind::Ice => self.ice,
+            DamageKind::Acid => self.acid,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
 pub struct Damage {
     pub dmg: f32,
     pub limit: f32,
-    pub multiplier: f32
+    pub multiplier: f32,
+    pub kind: DamageKind,
+    /// Chance from 0 to 1 of a critical hit.
+    pub crit_chance: f32,
 }
 
 impl Damage {
     pub fn damage_dealt(&self) -> f32 {
         if self.dmg* self.multiplier > self.limit {self.limit} else {self.dmg*self.multiplier}
     }
+
+    /// This damage, as dealt by `source` to `target`.
+    pub fn to(&self, target: Entity, source: DamageSource) -> DamageEvent {
+        DamageEvent {
+            target,
+            source,
+            amount: self.damage_dealt(),
+            kind: self.kind,
+            crit_chance: self.crit_chance,
+        }
+    }
 }
 
 #[derive(Component)]
@@ -56,6 +167,38 @@ impl From<(f32, f32)> for SpriteSize {
 }
 // endregion: --- Common Components
 
+// region: --- Projectile Components
+/// Turns the projectile towards the nearest opposing target, in radians per second.
+#[derive(Component)]
+pub struct Homing {
+    pub turn_rate: f32,
+}
+
+/// Keeps flying through enemies until `remaining` more have been hit.
+#[derive(Component)]
+pub struct Piercing {
+    pub remaining: u32,
+    pub hit: Vec<Entity>,
+}
+
+/// Rotates the heading at a constant rate, in radians per second.
+#[derive(Component)]
+pub struct Spin(pub f32);
+
+/// Sine-wave weave across the original heading.
+#[derive(Component)]
+pub struct Wave {
+    pub direction: Vec2,
+    pub speed: f32,
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub age: f32,
+}
+
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+// endregion: --- Projectile Components
+
 // region: --- Player Components
 #[derive(Component)]
 pub struct Player;
@@ -68,6 +211,32 @@ pub struct FromPlayer;
 #[derive(Component)]
 pub struct Enemy;
 
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnemyKind {
+    Grunt,
+    Weaver,
+    Spinner,
+    Turret,
+    Gunner,
+    Sentinel,
+}
+
+impl FromStr for EnemyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "grunt" => Ok(EnemyKind::Grunt),
+            "weaver" => Ok(EnemyKind::Weaver),
+            "spinner" => Ok(EnemyKind::Spinner),
+            "turret" => Ok(EnemyKind::Turret),
+            "gunner" => Ok(EnemyKind::Gunner),
+            "sentinel" => Ok(EnemyKind::Sentinel),
+            _ => Err(format!("unknown enemy archetype '{}'", s)),
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct FromEnemy;
 
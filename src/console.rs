@@ -0,0 +1,223 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use bevy::{input::InputSystem, prelude::*, window::ReceivedCharacter};
+
+const HISTORY_MAX: usize = 14;
+const CONSOLE_TOGGLE: char = '`';
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Console::default())
+            .register_console_command("help", "help", help_command)
+            .register_console_command("clear", "clear", clear_command)
+            .add_startup_system(console_setup_system)
+            .add_system_to_stage(CoreStage::PreUpdate, console_input_system.after(InputSystem))
+            .add_system(console_exec_system.exclusive_system())
+            .add_system(console_render_system);
+    }
+}
+
+/// Command handler: gets the world and the words after the command name,
+/// returns a line to print or an error message.
+pub type ConsoleHandler = fn(&mut World, &[&str]) -> Result<String, String>;
+
+struct ConsoleCommand {
+    usage: &'static str,
+    handler: ConsoleHandler,
+}
+
+/// Registry of console commands. Names may span several words (`"spawn enemy"`),
+/// the longest registered prefix of the typed line wins.
+#[derive(Default)]
+pub struct ConsoleCommands(BTreeMap<&'static str, ConsoleCommand>);
+
+pub trait ConsoleAppExt {
+    fn register_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        handler: ConsoleHandler,
+    ) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn register_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        handler: ConsoleHandler,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .0
+            .insert(name, ConsoleCommand { usage, handler });
+        self
+    }
+}
+
+/// Parse the `index`-th argument, with the command usage as the error.
+pub fn parse_arg<T: FromStr>(args: &[&str], index: usize, usage: &str) -> Result<T, String> {
+    args.get(index)
+        .and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| format!("usage: {}", usage))
+}
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    pending: Vec<String>,
+    history: Vec<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        if self.history.len() > HISTORY_MAX {
+            let overflow = self.history.len() - HISTORY_MAX;
+            self.history.drain(..overflow);
+        }
+    }
+}
+
+#[derive(Component)]
+struct ConsoleRoot;
+
+#[derive(Component)]
+struct ConsoleText;
+
+fn console_setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(0.),
+                    left: Val::Px(0.),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Percent(40.)),
+                align_items: AlignItems::FlexEnd,
+                padding: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.85).into(),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(ConsoleRoot)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 18.0,
+                            color: Color::rgb(0.7, 1., 0.7),
+                        },
+                    ),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(ConsoleText);
+        });
+}
+
+/// Captures typing while the console is open, and swallows the keyboard so
+/// gameplay systems don't react to it.
+fn console_input_system(
+    mut console: ResMut<Console>,
+    mut kb: ResMut<Input<KeyCode>>,
+    mut char_events: EventReader<ReceivedCharacter>,
+) {
+    let typed: Vec<char> = char_events.iter().map(|event| event.char).collect();
+    if kb.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+        kb.reset_all();
+        return;
+    }
+    if !console.open {
+        return;
+    }
+    for c in typed {
+        if c != CONSOLE_TOGGLE && !c.is_control() {
+            console.input.push(c);
+        }
+    }
+    if kb.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if kb.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        if !line.trim().is_empty() {
+            console.pending.push(line);
+        }
+    }
+    kb.reset_all();
+}
+
+fn console_exec_system(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        world.resource_mut::<Console>().print(format!("> {}", line));
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        // longest matching command name, then its handler with the remaining words
+        let found = {
+            let commands = world.resource::<ConsoleCommands>();
+            (1..=words.len()).rev().find_map(|len| {
+                commands
+                    .0
+                    .get(words[..len].join(" ").as_str())
+                    .map(|command| (len, command.handler))
+            })
+        };
+        let output = match found {
+            Some((len, handler)) => handler(world, &words[len..]),
+            None => Err(format!("unknown command '{}', try 'help'", words[0])),
+        };
+        let mut console = world.resource_mut::<Console>();
+        match output {
+            Ok(message) if message.is_empty() => {}
+            Ok(message) => console.print(message),
+            Err(message) => console.print(format!("error: {}", message)),
+        }
+    }
+}
+
+fn console_render_system(
+    console: Res<Console>,
+    mut root_query: Query<&mut Visibility, (With<ConsoleRoot>, Without<ConsoleText>)>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut visibility in root_query.iter_mut() {
+        visibility.is_visible = console.open;
+    }
+    for (mut text, mut visibility) in text_query.iter_mut() {
+        visibility.is_visible = console.open;
+        let mut value = console.history.join("\n");
+        value.push_str(&format!("\n> {}_", console.input));
+        text.sections[0].value = value;
+    }
+}
+
+fn help_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let usages: Vec<&str> = world
+        .resource::<ConsoleCommands>()
+        .0
+        .values()
+        .map(|command| command.usage)
+        .collect();
+    Ok(usages.join("\n"))
+}
+
+fn clear_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    world.resource_mut::<Console>().history.clear();
+    Ok(String::new())
+}
@@ -0,0 +1,397 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{Armour, Despawn, Health, Immunity, Player, Resistances},
+    events::{DamageEvent, DamageSource, EnemyHit, EnemyKilled, PlayerDamaged, PlayerDied},
+    shield::Shield,
+    status::StatusEffects,
+    GameRng, GameTime, PlayerState, KILL_HIT_STOP,
+};
+
+// Critical hits deal this many times the damage
+const CRIT_MULTIPLIER: f32 = 2.;
+
+/// Single place where damage is dealt: systems that detect hits send
+/// `DamageEvent`s, `damage_system` resolves them against the target's crit
+/// roll, `Resistances`, `Armour`, `Health.multiplier` and `Immunity`, leaves
+/// status effects behind, drains any `Shield` before `Health`, and reports
+/// the outcome.
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(immunity_system.before(DamageLabel))
+            .add_system(damage_system.label(DamageLabel));
+    }
+}
+
+/// Hit detection should run before this to have its damage dealt the same frame.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DamageLabel;
+
+fn immunity_system(game_time: Res<GameTime>, mut query: Query<&mut Immunity>) {
+    for mut immunity in query.iter_mut() {
+        immunity.timer.tick(game_time.delta());
+    }
+}
+
+type DamageTargetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static mut Health,
+        Option<&'static Resistances>,
+        Option<&'static Armour>,
+        Option<&'static mut Immunity>,
+        Option<&'static mut StatusEffects>,
+        Option<&'static mut Shield>,
+        Option<&'static Player>,
+    ),
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn damage_system(
+    mut commands: Commands,
+    mut game_time: ResMut<GameTime>,
+    mut player_state: ResMut<PlayerState>,
+    mut rng: ResMut<GameRng>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut query: DamageTargetQuery,
+    mut hit_events: EventWriter<EnemyHit>,
+    mut killed_events: EventWriter<EnemyKilled>,
+    mut damaged_events: EventWriter<PlayerDamaged>,
+    mut died_events: EventWriter<PlayerDied>,
+) {
+    for event in damage_events.iter() {
+        let (transform, mut health, resistances, armour, immunity, status, shield, player) = match query.get_mut(event.target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        // already dead, going at the end of the frame
+        if health.hp <= 0. {
+            continue;
+        }
+        if player.is_some() && player_state.god {
+            continue;
+        }
+        // burns were resisted when they caught, and tick through armour and immunity
+        let burn = matches!(event.source, DamageSource::Burning);
+        if !burn && immunity.as_ref().is_some_and(|immunity| immunity.active()) {
+            continue;
+        }
+
+        let amount = if burn {
+            event.amount * health.multiplier
+        } else {
+            let critical = event.crit_chance > 0. && rng.0.gen::<f32>() < event.crit_chance;
+            let amount = event.amount * if critical { CRIT_MULTIPLIER } else { 1. };
+            let amount = amount * resistances.map_or(1., |resistances| resistances.against(event.kind));
+            if amount <= 0. {
+                continue;
+            }
+            // the element gets through even when armour stops the hit itself,
+            // but not through a shield
+            let corrosion = status.as_ref().map_or(0., |status| status.corrosion());
+            if let Some(mut status) = status.filter(|_| !shield.as_ref().is_some_and(|shield| shield.is_up())) {
+                status.apply(event.kind, amount);
+            }
+            let armour = (armour.map_or(0., |armour| armour.0) - corrosion).max(0.);
+            (amount - armour).max(0.) * health.multiplier
+        };
+        if amount <= 0. {
+            continue;
+        }
+        let dealt = amount;
+        let amount = match shield {
+            Some(mut shield) if shield.is_up() => shield.absorb(amount),
+            _ => amount,
+        };
+        if amount > 0. {
+            health.hp -= amount;
+            if let Some(mut immunity) = immunity.filter(|_| !burn) {
+                immunity.hit();
+            }
+        }
+
+        let position = transform.translation;
+        let dead = health.hp <= 0.;
+        if dead {
+            commands.entity(event.target).insert(Despawn);
+        }
+        if player.is_some() {
+            // what the shield soaked up doesn't count against the player
+            if amount > 0. {
+                damaged_events.send(PlayerDamaged { amount, position });
+            }
+            if dead {
+                player_state.on = false;
+                died_events.send(PlayerDied { position });
+            }
+        } else {
+            hit_events.send(EnemyHit {
+                entity: event.target,
+                source: event.source,
+                amount: dealt,
+            });
+            if dead {
+                killed_events.send(EnemyKilled {
+                    entity: event.target,
+                    position,
+                });
+                game_time.hit_stop(KILL_HIT_STOP);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{components::DamageKind, status::Status, tuning::Tuning};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.insert_resource(GameTime::default())
+            .insert_resource(PlayerState::new(&Tuning::default()))
+            .insert_resource(GameRng(StdRng::seed_from_u64(0)))
+            .add_event::<DamageEvent>()
+            .add_event::<EnemyHit>()
+            .add_event::<EnemyKilled>()
+            .add_event::<PlayerDamaged>()
+            .add_event::<PlayerDied>()
+            .add_system(damage_system);
+        app
+    }
+
+    fn spawn(app: &mut App, hp: f32, multiplier: f32) -> Entity {
+        app.world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Health { hp, multiplier })
+            .id()
+    }
+
+    fn hit(app: &mut App, target: Entity, amount: f32, crit_chance: f32) {
+        app.world.resource_mut::<Events<DamageEvent>>().send(DamageEvent {
+            target,
+            source: DamageSource::Player,
+            amount,
+            kind: DamageKind::Kinetic,
+            crit_chance,
+        });
+        app.update();
+    }
+
+    fn hit_with(app: &mut App, target: Entity, source: DamageSource, kind: DamageKind, amount: f32) {
+        app.world.resource_mut::<Events<DamageEvent>>().send(DamageEvent {
+            target,
+            source,
+            amount,
+            kind,
+            crit_chance: 0.,
+        });
+        app.update();
+    }
+
+    fn hp(app: &App, target: Entity) -> f32 {
+        app.world.get::<Health>(target).unwrap().hp
+    }
+
+    #[test]
+    fn crits_double_damage() {
+        let mut app = app();
+        let target = spawn(&mut app, 100., 1.);
+        hit(&mut app, target, 3., 0.);
+        assert_eq!(hp(&app, target), 97.);
+        hit(&mut app, target, 3., 1.);
+        assert_eq!(hp(&app, target), 91.);
+    }
+
+    #[test]
+    fn crits_follow_the_chance() {
+        let mut app = app();
+        let target = spawn(&mut app, 100_000., 1.);
+        for _ in 0..1000 {
+            hit(&mut app, target, 1., 0.25);
+        }
+        // 1000 hits, a quarter of them doubled
+        let crits = 100_000. - hp(&app, target) - 1000.;
+        assert!((200. ..300.).contains(&crits), "{} crits", crits);
+    }
+
+    #[test]
+    fn armour_then_multiplier() {
+        let mut app = app();
+        let target = spawn(&mut app, 10., 2.);
+        app.world.entity_mut(target).insert(Armour(1.));
+        hit(&mut app, target, 3., 0.);
+        assert_eq!(hp(&app, target), 6.);
+        // armour soaks the whole hit
+        hit(&mut app, target, 0.5, 0.);
+        assert_eq!(hp(&app, target), 6.);
+    }
+
+    #[test]
+    fn immunity_ignores_hits_and_restarts_on_hit() {
+        let mut app = app();
+        let target = spawn(&mut app, 10., 1.);
+        app.world.entity_mut(target).insert(Immunity::new(1., 5.));
+        hit(&mut app, target, 1., 0.);
+        assert_eq!(hp(&app, target), 10.);
+        let mut immunity = app.world.get_mut::<Immunity>(target).unwrap();
+        immunity.timer.tick(Duration::from_secs(1));
+        assert!(!immunity.active());
+        hit(&mut app, target, 1., 0.);
+        assert_eq!(hp(&app, target), 9.);
+        assert!(app.world.get::<Immunity>(target).unwrap().active());
+        hit(&mut app, target, 1., 0.);
+        assert_eq!(hp(&app, target), 9.);
+    }
+
+    #[test]
+    fn god_mode_protects_only_the_player() {
+        let mut app = app();
+        app.world.resource_mut::<PlayerState>().god = true;
+        let player = spawn(&mut app, 3., 1.);
+        app.world.entity_mut(player).insert(Player);
+        let enemy = spawn(&mut app, 3., 1.);
+        hit(&mut app, player, 1., 0.);
+        hit(&mut app, enemy, 1., 0.);
+        assert_eq!(hp(&app, player), 3.);
+        assert_eq!(hp(&app, enemy), 2.);
+    }
+
+    #[test]
+    fn dead_targets_are_skipped() {
+        let mut app = app();
+        let target = spawn(&mut app, 1., 1.);
+        app.world.resource_mut::<Events<DamageEvent>>().send(DamageEvent {
+            target,
+            source: DamageSource::Player,
+            amount: 5.,
+            kind: DamageKind::Kinetic,
+            crit_chance: 0.,
+        });
+        hit(&mut app, target, 5., 0.);
+        // the second hit in the same frame neither lands nor kills again
+        assert_eq!(hp(&app, target), -4.);
+        assert_eq!(app.world.resource::<Events<EnemyHit>>().len(), 1);
+        assert_eq!(app.world.resource::<Events<EnemyKilled>>().len(), 1);
+    }
+
+    #[test]
+    fn resistance_then_armour_then_multiplier() {
+        let mut app = app();
+        let target = spawn(&mut app, 20., 2.);
+        app.world.entity_mut(target).insert(Armour(1.)).insert(Resistances {
+            fire: 0.5,
+            ..Resistances::NONE
+        });
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Fire, 6.);
+        assert_eq!(hp(&app, target), 16.);
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Kinetic, 6.);
+        assert_eq!(hp(&app, target), 6.);
+    }
+
+    #[test]
+    fn corrosion_eats_armour() {
+        let mut app = app();
+        let target = spawn(&mut app, 20., 1.);
+        app.world.entity_mut(target).insert(Armour(2.)).insert(StatusEffects::default());
+        // armour stops the first hit, its acid still gets through
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 2.);
+        assert_eq!(hp(&app, target), 20.);
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 2.);
+        assert_eq!(hp(&app, target), 19.);
+        // armour never goes negative however much acid piles up
+        for _ in 0..3 {
+            hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 2.);
+        }
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Kinetic, 2.);
+        assert_eq!(hp(&app, target), 11.);
+    }
+
+    #[test]
+    fn full_resistance_blocks_the_status_too() {
+        let mut app = app();
+        let target = spawn(&mut app, 20., 1.);
+        app.world.entity_mut(target).insert(StatusEffects::default()).insert(Resistances {
+            acid: 0.,
+            ..Resistances::NONE
+        });
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 5.);
+        assert_eq!(hp(&app, target), 20.);
+        assert!(!app.world.get::<StatusEffects>(target).unwrap().has(Status::Corroded));
+    }
+
+    #[test]
+    fn burns_tick_through_armour_and_immunity() {
+        let mut app = app();
+        let target = spawn(&mut app, 20., 2.);
+        app.world
+            .entity_mut(target)
+            .insert(Armour(5.))
+            .insert(Immunity::new(1., 1.))
+            .insert(Resistances {
+                fire: 0.,
+                ..Resistances::NONE
+            });
+        hit_with(&mut app, target, DamageSource::Burning, DamageKind::Fire, 1.5);
+        assert_eq!(hp(&app, target), 17.);
+        let mut hits = app.world.resource_mut::<Events<EnemyHit>>();
+        assert!(matches!(hits.drain().next().unwrap().source, DamageSource::Burning));
+    }
+
+    #[test]
+    fn shield_soaks_before_health() {
+        let mut app = app();
+        let target = spawn(&mut app, 10., 1.);
+        app.world.entity_mut(target).insert(Shield::new(4., None));
+        hit(&mut app, target, 3., 0.);
+        assert_eq!(hp(&app, target), 10.);
+        assert_eq!(app.world.get::<Shield>(target).unwrap().hp, 1.);
+        // what the shield can't hold spills over
+        hit(&mut app, target, 3., 0.);
+        assert_eq!(hp(&app, target), 8.);
+        assert!(!app.world.get::<Shield>(target).unwrap().is_up());
+    }
+
+    #[test]
+    fn shield_keeps_statuses_off() {
+        let mut app = app();
+        let target = spawn(&mut app, 10., 1.);
+        app.world.entity_mut(target).insert(StatusEffects::default()).insert(Shield::new(4., None));
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 1.);
+        assert!(!app.world.get::<StatusEffects>(target).unwrap().has(Status::Corroded));
+        app.world.get_mut::<Shield>(target).unwrap().lower();
+        hit_with(&mut app, target, DamageSource::Player, DamageKind::Acid, 1.);
+        assert!(app.world.get::<StatusEffects>(target).unwrap().has(Status::Corroded));
+    }
+
+    #[test]
+    fn shielded_player_takes_no_damage_or_immunity() {
+        let mut app = app();
+        let player = spawn(&mut app, 3., 1.);
+        app.world
+            .entity_mut(player)
+            .insert(Player)
+            .insert(Immunity::new(0., 5.))
+            .insert(Shield::new(2., None));
+        app.world.get_mut::<Immunity>(player).unwrap().timer.tick(Duration::ZERO);
+        hit(&mut app, player, 1., 0.);
+        assert_eq!(hp(&app, player), 3.);
+        assert!(!app.world.get::<Immunity>(player).unwrap().active());
+        assert!(app.world.resource::<Events<PlayerDamaged>>().is_empty());
+        // only the part that got through is reported
+        hit(&mut app, player, 3., 0.);
+        assert_eq!(hp(&app, player), 1.);
+        let mut damaged = app.world.resource_mut::<Events<PlayerDamaged>>();
+        assert_eq!(damaged.drain().map(|event| event.amount).collect::<Vec<_>>(), [2.]);
+    }
+}
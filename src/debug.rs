@@ -0,0 +1,263 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    components::{Despawn, Enemy, Explosion, FromEnemy, FromPlayer, Health, Immunity, Player, SpriteSize},
+    enemy::{Director, EnemyPopulation, Formation, Waves},
+    input::PlayerActions,
+    shield::{Shield, ShieldAbility},
+    status::{Status, StatusEffects},
+    GameTime, PlayerState,
+};
+
+const ORBIT_DOTS: usize = 32;
+const AIM_DOTS: usize = 12;
+const PLAYER_AIM_LENGTH: f32 = 300.;
+const DEBUG_Z: f32 = 50.;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .insert_resource(DebugOverlay::default())
+            .add_startup_system(debug_text_setup_system)
+            .add_system(debug_toggle_system)
+            .add_system(debug_shapes_system)
+            .add_system(debug_text_system);
+    }
+}
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+/// Sprite drawn by the overlay, kept from frame to frame and hidden while
+/// not needed.
+#[derive(Component)]
+struct DebugShape;
+
+#[derive(Component)]
+struct DebugText;
+
+fn debug_text_setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 18.0,
+                    color: Color::YELLOW,
+                },
+            ),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(DebugText);
+}
+
+fn debug_toggle_system(
+    kb: Res<Input<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut query: Query<&mut Visibility, With<DebugText>>,
+) {
+    if kb.just_pressed(KeyCode::F1) {
+        overlay.enabled = !overlay.enabled;
+        for mut visibility in query.iter_mut() {
+            visibility.is_visible = overlay.enabled;
+        }
+    }
+}
+
+/// Everything with a collision box, and what it belongs to.
+type HitboxQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static SpriteSize,
+        Option<&'static Player>,
+        Option<&'static Enemy>,
+        Option<&'static FromPlayer>,
+        Option<&'static FromEnemy>,
+    ),
+    Without<DebugShape>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn debug_shapes_system(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    actions: Res<PlayerActions>,
+    mut shape_query: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<DebugShape>>,
+    hitbox_query: HitboxQuery,
+    formation_query: Query<&Formation, With<Enemy>>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<DebugShape>)>,
+    player_query: Query<&Transform, (With<Player>, Without<DebugShape>)>,
+) {
+    // position, size and colour of every rectangle to draw this frame
+    let mut rects = Vec::new();
+    if overlay.enabled {
+        // collision boxes, as used by `collide`
+        for (transform, size, player, enemy, from_player, from_enemy) in hitbox_query.iter() {
+            let color = match (player, enemy, from_player, from_enemy) {
+                (Some(_), ..) => Color::rgba(0., 1., 0., 0.3),
+                (_, Some(_), ..) => Color::rgba(1., 0., 0., 0.3),
+                (.., Some(_), _) => Color::rgba(0., 1., 1., 0.5),
+                (.., Some(_)) => Color::rgba(1., 0.5, 0., 0.5),
+                _ => Color::rgba(1., 1., 1., 0.3),
+            };
+            let size = size.0 * transform.scale.truncate();
+            rects.push((transform.translation.truncate(), size, color));
+        }
+
+        // formation pivots and orbits
+        for formation in formation_query.iter() {
+            let pivot = Vec2::new(formation.pivot.0, formation.pivot.1);
+            let radius = Vec2::new(formation.radius.0, formation.radius.1);
+            rects.push((pivot, Vec2::splat(8.), Color::FUCHSIA));
+            for i in 0..ORBIT_DOTS {
+                let angle = i as f32 / ORBIT_DOTS as f32 * TAU;
+                let dot = pivot + radius * Vec2::new(angle.cos(), angle.sin());
+                rects.push((dot, Vec2::splat(3.), Color::rgba(1., 0., 1., 0.6)));
+            }
+        }
+
+        if let Ok(player_tf) = player_query.get_single() {
+            let player = player_tf.translation.truncate();
+            // where the player is asking to fire, 0 up and clockwise
+            if let Some(aim) = actions.aim {
+                let direction = Vec2::new(aim.sin(), aim.cos());
+                for i in 1..=AIM_DOTS {
+                    let dot = player + direction * PLAYER_AIM_LENGTH * i as f32 / AIM_DOTS as f32;
+                    rects.push((dot, Vec2::splat(4.), Color::rgba(0., 1., 0., 0.6)));
+                }
+            }
+            // enemy aim lines
+            for enemy_tf in enemy_query.iter() {
+                let origin = enemy_tf.translation.truncate();
+                for i in 1..AIM_DOTS {
+                    let dot = origin.lerp(player, i as f32 / AIM_DOTS as f32);
+                    rects.push((dot, Vec2::splat(3.), Color::rgba(1., 1., 0., 0.5)));
+                }
+            }
+        }
+    }
+
+    // reuse the shapes from last frame, hide the spare ones and spawn what's missing
+    let mut rects = rects.into_iter();
+    for (mut transform, mut sprite, mut visibility) in shape_query.iter_mut() {
+        match rects.next() {
+            Some((position, size, color)) => {
+                transform.translation = position.extend(DEBUG_Z);
+                sprite.custom_size = Some(size);
+                sprite.color = color;
+                visibility.is_visible = true;
+            }
+            None if visibility.is_visible => visibility.is_visible = false,
+            None => {}
+        }
+    }
+    for (position, size, color) in rects {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(DEBUG_Z)),
+                ..default()
+            })
+            .insert(DebugShape);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn debug_text_system(
+    overlay: Res<DebugOverlay>,
+    diagnostics: Res<Diagnostics>,
+    population: Res<EnemyPopulation>,
+    director: Res<Director>,
+    waves: Res<Waves>,
+    player_state: Res<PlayerState>,
+    game_time: Res<GameTime>,
+    player_query: Query<(&Health, &Immunity, &StatusEffects, &Shield, &ShieldAbility), With<Player>>,
+    // the overlay's own shapes don't count
+    entity_query: Query<(), Without<DebugShape>>,
+    player_laser_query: Query<(), With<FromPlayer>>,
+    enemy_laser_query: Query<(), With<FromEnemy>>,
+    explosion_query: Query<(), With<Explosion>>,
+    mut query: Query<&mut Text, With<DebugText>>,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average())
+        .unwrap_or(0.);
+    let frame_time = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|frame_time| frame_time.average())
+        .unwrap_or(0.);
+
+    let mut lines = vec![
+        format!("fps {:.0} ({:.2} ms)", fps, frame_time * 1000.),
+        format!("time scale {:.2}", game_time.time_scale()),
+        format!("entities {}", entity_query.iter().count()),
+        format!("enemies {}", population.total()),
+    ];
+    for (kind, count) in population.iter() {
+        lines.push(format!("  {:?} {}", kind, count));
+    }
+    lines.push(format!("wave {} {:?}", waves.number, waves.stage));
+    lines.push(format!(
+        "director budget {:.1} threat {:.1}/{:.1}",
+        director.budget, director.threat, director.max_threat
+    ));
+    lines.push(format!(
+        "lasers player {} enemy {}",
+        player_laser_query.iter().count(),
+        enemy_laser_query.iter().count()
+    ));
+    lines.push(format!("explosions {}", explosion_query.iter().count()));
+
+    let timer_line = |name: &str, timer: &Timer| {
+        format!("{} {:.2}/{:.2}", name, timer.elapsed_secs(), timer.duration().as_secs_f32())
+    };
+    lines.push(format!("player on {}", player_state.on));
+    if let Ok((health, immunity, status, shield, ability)) = player_query.get_single() {
+        lines.push(format!("  hp {}", health.hp));
+        lines.push(timer_line("  immunity", &immunity.timer));
+        lines.push(format!("  shield {:.1}/{}", shield.hp, shield.max));
+        lines.push(timer_line("  shield cooldown", ability.cooldown()));
+        let effects: Vec<Status> = Status::ALL.into_iter().filter(|effect| status.has(*effect)).collect();
+        lines.push(format!("  status {:?}", effects));
+    }
+    lines.push(timer_line("fire", &player_state.fire_cooldown));
+    lines.push(timer_line("respawn", &player_state.spawn_cooldown));
+    lines.push(format!(
+        "delta x {:.2} y {:.2}",
+        player_state.delta_x, player_state.delta_y
+    ));
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+
+use crate::{
+    components::{Despawn, Health, Player},
+    console::{parse_arg, ConsoleAppExt},
+    events::PlayerDied,
+    input::InputSource,
+    tuning::Tuning,
+    GameTime,
+};
+
+// Largest performance adjustment either way, in difficulty levels
+const ADJUST_RANGE: f32 = 0.25;
+// How fast the adjustment drifts towards its target, in levels per second
+const ADJUST_RATE: f32 = 0.02;
+// Immediate easing off when the player dies
+const DEATH_RELIEF: f32 = 0.15;
+// How strongly each aspect follows the difficulty level
+const FIRE_RATE_WEIGHT: f32 = 1.;
+const PROJECTILE_SPEED_WEIGHT: f32 = 0.5;
+const HP_WEIGHT: f32 = 1.;
+const SPAWN_RATE_WEIGHT: f32 = 0.75;
+// Nothing scales below this share of its base value
+const MIN_SCALE: f32 = 0.3;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        let tuning = app.world.get_resource::<Tuning>().cloned().unwrap_or_default();
+        app.insert_resource(Difficulty::new(tuning.difficulty))
+            .add_startup_system(difficulty_menu_setup_system)
+            .add_system(difficulty_menu_system)
+            .add_system(difficulty_system)
+            .register_console_command("difficulty", "difficulty [easy|normal|hard]", difficulty_command);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DifficultyPreset {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl FromStr for DifficultyPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(DifficultyPreset::Easy),
+            "normal" => Ok(DifficultyPreset::Normal),
+            "hard" => Ok(DifficultyPreset::Hard),
+            _ => Err(format!("unknown difficulty '{}'", s)),
+        }
+    }
+}
+
+impl DifficultyPreset {
+    pub const ALL: [DifficultyPreset; 3] = [DifficultyPreset::Easy, DifficultyPreset::Normal, DifficultyPreset::Hard];
+
+    /// Level at the start, level it tends to, and seconds to get about two
+    /// thirds of the way there.
+    fn curve(&self) -> (f32, f32, f32) {
+        match self {
+            DifficultyPreset::Easy => (0.7, 1.3, 240.),
+            DifficultyPreset::Normal => (1., 1.8, 180.),
+            DifficultyPreset::Hard => (1.3, 2.4, 120.),
+        }
+    }
+}
+
+/// How hard the game currently is: a level following the preset's curve
+/// over play time, nudged up or down by how the player is doing. Enemies
+/// scale their fire rate, projectile speed and hp, and the spawn director
+/// its budget, from it.
+pub struct Difficulty {
+    pub preset: DifficultyPreset,
+    elapsed: f32,
+    adjustment: f32,
+}
+
+impl Difficulty {
+    pub fn new(preset: DifficultyPreset) -> Self {
+        Self {
+            preset,
+            elapsed: 0.,
+            adjustment: 0.,
+        }
+    }
+
+    /// How far along the curve the game is, 0 at the start and approaching 1.
+    pub fn progress(&self) -> f32 {
+        let (_, _, ramp) = self.preset.curve();
+        1. - (-self.elapsed / ramp).exp()
+    }
+
+    /// 1 is the baseline the archetypes are tuned for.
+    pub fn level(&self) -> f32 {
+        let (start, peak, _) = self.preset.curve();
+        start + (peak - start) * self.progress() + self.adjustment
+    }
+
+    fn scale(&self, weight: f32) -> f32 {
+        (1. + (self.level() - 1.) * weight).max(MIN_SCALE)
+    }
+
+    pub fn fire_rate(&self) -> f32 {
+        self.scale(FIRE_RATE_WEIGHT)
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        self.scale(PROJECTILE_SPEED_WEIGHT)
+    }
+
+    pub fn hp(&self) -> f32 {
+        self.scale(HP_WEIGHT)
+    }
+
+    pub fn spawn_rate(&self) -> f32 {
+        self.scale(SPAWN_RATE_WEIGHT)
+    }
+}
+
+fn difficulty_system(
+    mut difficulty: ResMut<Difficulty>,
+    game_time: Res<GameTime>,
+    tuning: Res<Tuning>,
+    player_query: Query<&Health, With<Player>>,
+    mut died_events: EventReader<PlayerDied>,
+) {
+    let dt = game_time.delta_seconds();
+    difficulty.elapsed += dt;
+    for _ in died_events.iter() {
+        difficulty.adjustment -= DEATH_RELIEF;
+    }
+    if let Ok(health) = player_query.get_single() {
+        // healthy players get pushed harder, struggling ones get some slack
+        let health = (health.hp / tuning.player_hp.max(f32::EPSILON)).clamp(0., 1.);
+        let target = (health - 0.5) * 2. * ADJUST_RANGE;
+        let step = ADJUST_RATE * dt;
+        difficulty.adjustment += (target - difficulty.adjustment).clamp(-step, step);
+    }
+    difficulty.adjustment = difficulty.adjustment.clamp(-ADJUST_RANGE, ADJUST_RANGE);
+}
+
+/// Pre-game screen picking the preset. The game stays paused while it's up.
+#[derive(Component)]
+struct DifficultyMenu;
+
+fn difficulty_menu_setup_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    source: Res<InputSource>,
+    difficulty: Res<Difficulty>,
+    mut game_time: ResMut<GameTime>,
+) {
+    // nobody to ask in headless runs, and agents pick through tuning
+    if windows.get_primary().is_none() || *source == InputSource::Agent {
+        return;
+    }
+    game_time.paused = true;
+
+    let mut lines = vec!["Choose difficulty\n".to_string()];
+    for (i, preset) in DifficultyPreset::ALL.iter().enumerate() {
+        let marker = if *preset == difficulty.preset { ">" } else { " " };
+        lines.push(format!("{} {} {:?}", marker, i + 1, preset));
+    }
+    lines.push("\nEnter keeps the marked one".to_string());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::rgba(0., 0., 0., 0.8)),
+            ..default()
+        })
+        .insert(DifficultyMenu)
+        .with_children(|menu| {
+            menu.spawn_bundle(TextBundle {
+                text: Text::from_section(
+                    lines.join("\n"),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ..default()
+            });
+        });
+}
+
+fn difficulty_menu_system(
+    mut commands: Commands,
+    kb: Res<Input<KeyCode>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut game_time: ResMut<GameTime>,
+    query: Query<Entity, With<DifficultyMenu>>,
+) {
+    let menu = match query.get_single() {
+        Ok(menu) => menu,
+        Err(_) => return,
+    };
+    let picked = if kb.just_pressed(KeyCode::Key1) {
+        Some(DifficultyPreset::Easy)
+    } else if kb.just_pressed(KeyCode::Key2) {
+        Some(DifficultyPreset::Normal)
+    } else if kb.just_pressed(KeyCode::Key3) {
+        Some(DifficultyPreset::Hard)
+    } else if kb.just_pressed(KeyCode::Return) {
+        Some(difficulty.preset)
+    } else {
+        None
+    };
+    match picked {
+        Some(preset) => {
+            *difficulty = Difficulty::new(preset);
+            commands.entity(menu).insert(Despawn);
+            game_time.paused = false;
+        }
+        // no unpausing past the menu
+        None => game_time.paused = true,
+    }
+}
+
+fn difficulty_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let mut difficulty = world.resource_mut::<Difficulty>();
+    if !args.is_empty() {
+        difficulty.preset = parse_arg(args, 0, "difficulty [easy|normal|hard]")?;
+    }
+    Ok(format!("difficulty {:?}, level {:.2}", difficulty.preset, difficulty.level()))
+}
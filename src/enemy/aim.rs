@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+/// How well an archetype leads and how much its shots scatter.
+#[derive(Clone, Copy)]
+pub struct AimParams {
+    /// 0 shoots at where the player is, 1 at where it will be.
+    pub lead: f32,
+    /// Largest random error either side of the aim, in radians.
+    pub inaccuracy: f32,
+}
+
+/// Time until a projectile at `speed` from the origin meets a target at
+/// `offset` moving at `velocity`, if it can catch it at all.
+fn intercept_time(offset: Vec2, velocity: Vec2, speed: f32) -> Option<f32> {
+    // |offset + velocity * t| = speed * t
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2. * offset.dot(velocity);
+    let c = offset.length_squared();
+    if a.abs() < f32::EPSILON {
+        return (b < 0.).then(|| -c / b);
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2. * a), (-b + root) / (2. * a)]
+        .into_iter()
+        .filter(|t| *t > 0.)
+        .reduce(f32::min)
+}
+
+/// Direction to shoot from `from` at a target at `target` moving at
+/// `velocity` (pixels per second) with a projectile of `speed` pixels per
+/// second. `skill` from 0 to 1 scales the lead down and the error up.
+pub fn lead_direction(
+    params: &AimParams,
+    skill: f32,
+    from: Vec2,
+    target: Vec2,
+    velocity: Vec2,
+    speed: f32,
+    rng: &mut impl Rng,
+) -> Vec2 {
+    let skill = skill.clamp(0., 1.);
+    let offset = target - from;
+    let lead = params.lead * skill;
+    let aim_point = match intercept_time(offset, velocity, speed) {
+        Some(t) if lead > 0. => target + velocity * t * lead,
+        _ => target,
+    };
+    let direction = (aim_point - from).normalize_or_zero();
+    let error = params.inaccuracy * (2. - skill);
+    if error > 0. && direction != Vec2::ZERO {
+        Vec2::from_angle(rng.gen_range(-error..=error)).rotate(direction)
+    } else {
+        direction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const EXACT: AimParams = AimParams {
+        lead: 1.,
+        inaccuracy: 0.,
+    };
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(actual.abs_diff_eq(expected, 1e-4), "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn intercept_standing_target() {
+        assert_eq!(intercept_time(Vec2::new(300., 0.), Vec2::ZERO, 100.), Some(3.));
+    }
+
+    #[test]
+    fn intercept_crossing_target() {
+        // |(300t, 400)| = 500t
+        let t = intercept_time(Vec2::new(0., 400.), Vec2::new(300., 0.), 500.).unwrap();
+        assert!((t - 1.).abs() < 1e-4, "{}", t);
+    }
+
+    #[test]
+    fn intercept_at_equal_speed() {
+        assert_eq!(intercept_time(Vec2::new(100., 0.), Vec2::new(-50., 0.), 50.), Some(1.));
+        assert_eq!(intercept_time(Vec2::new(100., 0.), Vec2::new(50., 0.), 50.), None);
+    }
+
+    #[test]
+    fn intercept_target_outrunning() {
+        assert_eq!(intercept_time(Vec2::new(100., 0.), Vec2::new(200., 0.), 100.), None);
+    }
+
+    #[test]
+    fn lead_aims_at_intercept() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let direction = lead_direction(&EXACT, 1., Vec2::ZERO, Vec2::new(0., 400.), Vec2::new(300., 0.), 500., &mut rng);
+        assert_close(direction, Vec2::new(0.6, 0.8));
+    }
+
+    #[test]
+    fn no_lead_aims_at_target() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (target, velocity) = (Vec2::new(0., 400.), Vec2::new(300., 0.));
+        let no_lead = AimParams { lead: 0., ..EXACT };
+        assert_close(lead_direction(&no_lead, 1., Vec2::ZERO, target, velocity, 500., &mut rng), Vec2::Y);
+        // no skill, no lead either
+        assert_close(lead_direction(&EXACT, 0., Vec2::ZERO, target, velocity, 500., &mut rng), Vec2::Y);
+    }
+
+    #[test]
+    fn inaccuracy_stays_in_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sloppy = AimParams {
+            lead: 0.,
+            inaccuracy: 0.1,
+        };
+        for _ in 0..100 {
+            let direction = lead_direction(&sloppy, 1., Vec2::ZERO, Vec2::new(0., 400.), Vec2::ZERO, 500., &mut rng);
+            assert!((direction.length() - 1.).abs() < 1e-4);
+            assert!(direction.angle_between(Vec2::Y).abs() <= 0.1 + 1e-4);
+        }
+    }
+
+    #[test]
+    fn intercept_ignores_nan() {
+        assert_eq!(intercept_time(Vec2::new(100., 0.), Vec2::new(f32::NAN, 0.), 100.), None);
+        assert_eq!(intercept_time(Vec2::new(100., 0.), Vec2::ZERO, f32::NAN), None);
+        assert_eq!(intercept_time(Vec2::new(f32::NAN, 0.), Vec2::ZERO, 100.), None);
+    }
+
+    #[test]
+    fn lead_on_top_of_target_is_zero() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sloppy = AimParams { inaccuracy: 0.1, ..EXACT };
+        let direction = lead_direction(&sloppy, 1., Vec2::ONE, Vec2::ONE, Vec2::new(300., 0.), 500., &mut rng);
+        assert_eq!(direction, Vec2::ZERO);
+    }
+
+    #[test]
+    fn lead_never_returns_nan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sloppy = AimParams { inaccuracy: 0.1, ..EXACT };
+        let (target, velocity) = (Vec2::new(0., 400.), Vec2::new(300., 0.));
+        // a NaN velocity falls back to aiming straight at the target
+        let direction = lead_direction(&EXACT, 1., Vec2::ZERO, target, Vec2::new(f32::NAN, 0.), 500., &mut rng);
+        assert_close(direction, Vec2::Y);
+        let cases = [
+            lead_direction(&sloppy, 1., Vec2::ZERO, Vec2::new(f32::NAN, 400.), velocity, 500., &mut rng),
+            lead_direction(&sloppy, 1., Vec2::ZERO, target, velocity, f32::NAN, &mut rng),
+            lead_direction(&sloppy, f32::NAN, Vec2::ZERO, target, velocity, 500., &mut rng),
+            lead_direction(&sloppy, 1., Vec2::new(f32::INFINITY, 0.), target, velocity, 500., &mut rng),
+        ];
+        for direction in cases {
+            assert!(direction.is_finite(), "{}", direction);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    components::{DamageKind, EnemyKind, Resistances},
+    projectile::ProjectileType,
+    shield::ShieldRegen,
+};
+
+use super::{aim::AimParams, behaviour::{BehaviourParams, NoTarget}, enrage::EnrageParams, pattern::FirePattern};
+
+/// Per-archetype tuning, looked up from the `EnemyKind` on each enemy.
+pub struct EnemyArchetype {
+    pub hp: f32,
+    /// Taken off every hit, see `Armour`.
+    pub armour: f32,
+    pub resistances: Resistances,
+    /// Absorbing layer on top of `hp`, 0 for none, see `Shield`.
+    pub shield: f32,
+    pub shield_regen: Option<ShieldRegen>,
+    /// Element of every shot it fires.
+    pub damage_kind: DamageKind,
+    /// Rhai script under `assets/` driving movement and firing, replaces the
+    /// built-in formation orbit and aimed shot when set.
+    pub script: Option<&'static str>,
+    /// What each shot, built-in or scripted, fires.
+    pub projectile: ProjectileType,
+    /// Emitter shape for the built-in fire, unused by scripted archetypes.
+    pub pattern: FirePattern,
+    /// Lead and scatter of aimed shots.
+    pub aim: AimParams,
+    /// Salvos per `enemy_fire_cooldown`.
+    pub fire_rate: f32,
+    /// Relative chance of being picked by the spawn director.
+    pub spawn_weight: u32,
+    /// Budget the director spends on one, and the threat it poses while alive.
+    pub cost: f32,
+    pub enrage: Option<EnrageParams>,
+    /// Formation-breaking state machine, `None` just orbits (scripted
+    /// archetypes move themselves).
+    pub behaviour: Option<BehaviourParams>,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 6] = [
+        EnemyKind::Grunt,
+        EnemyKind::Weaver,
+        EnemyKind::Spinner,
+        EnemyKind::Turret,
+        EnemyKind::Gunner,
+        EnemyKind::Sentinel,
+    ];
+
+    pub fn archetype(&self) -> EnemyArchetype {
+        match self {
+            EnemyKind::Grunt => EnemyArchetype {
+                hp: 2.,
+                armour: 0.,
+                resistances: Resistances::NONE,
+                shield: 0.,
+                shield_regen: None,
+                damage_kind: DamageKind::Kinetic,
+                script: None,
+                projectile: ProjectileType::Laser,
+                pattern: FirePattern::Aimed,
+                aim: AimParams {
+                    lead: 0.5,
+                    inaccuracy: 0.08,
+                },
+                fire_rate: 1.,
+                spawn_weight: 5,
+                cost: 1.,
+                enrage: Some(EnrageParams {
+                    low_hp: Some(0.5),
+                    squad_losses: Some(2),
+                    squad_radius: 400.,
+                    time_alive: Some(20.),
+                    duration: 4.,
+                    cooldown: 6.,
+                    speed: 1.5,
+                    fire_rate: 1.5,
+                    damage: 1.5,
+                }),
+                behaviour: Some(BehaviourParams {
+                    orbit_time: (4., 8.),
+                    dive_range: Some(450.),
+                    strafe: true,
+                    flee_hp: None,
+                    dive_speed: 2.2,
+                    strafe_speed: 1.5,
+                    no_target: NoTarget::Celebrate,
+                }),
+            },
+            EnemyKind::Weaver => EnemyArchetype {
+                hp: 3.,
+                armour: 0.,
+                resistances: Resistances {
+                    acid: 0.5,
+                    ..Resistances::NONE
+                },
+                shield: 0.,
+                shield_regen: None,
+                damage_kind: DamageKind::Acid,
+                script: Some("scripts/weaver.rhai"),
+                projectile: ProjectileType::Sine,
+                pattern: FirePattern::Aimed,
+                aim: AimParams {
+                    lead: 0.,
+                    inaccuracy: 0.,
+                },
+                fire_rate: 1.,
+                spawn_weight: 3,
+                cost: 2.,
+                enrage: Some(EnrageParams {
+                    low_hp: Some(0.4),
+                    squad_losses: Some(1),
+                    squad_radius: 500.,
+                    time_alive: None,
+                    duration: 3.,
+                    cooldown: 5.,
+                    speed: 1.8,
+                    fire_rate: 1.3,
+                    damage: 1.25,
+                }),
+                behaviour: None,
+            },
+            EnemyKind::Spinner => EnemyArchetype {
+                hp: 4.,
+                armour: 0.,
+                resistances: Resistances {
+                    fire: 0.,
+                    ice: 1.5,
+                    ..Resistances::NONE
+                },
+                shield: 0.,
+                shield_regen: None,
+                damage_kind: DamageKind::Fire,
+                script: None,
+                projectile: ProjectileType::Laser,
+                pattern: FirePattern::Spiral {
+                    arms: 3,
+                    angular_speed: 1.5,
+                },
+                aim: AimParams {
+                    lead: 0.,
+                    inaccuracy: 0.,
+                },
+                fire_rate: 4.,
+                spawn_weight: 1,
+                cost: 3.,
+                enrage: Some(EnrageParams {
+                    low_hp: Some(0.5),
+                    squad_losses: None,
+                    squad_radius: 0.,
+                    time_alive: Some(15.),
+                    duration: 3.,
+                    cooldown: 8.,
+                    speed: 1.2,
+                    fire_rate: 1.6,
+                    damage: 1.,
+                }),
+                behaviour: Some(BehaviourParams {
+                    orbit_time: (6., 10.),
+                    dive_range: None,
+                    strafe: true,
+                    flee_hp: Some(0.25),
+                    dive_speed: 1.,
+                    strafe_speed: 1.2,
+                    no_target: NoTarget::Patrol,
+                }),
+            },
+            EnemyKind::Turret => EnemyArchetype {
+                hp: 5.,
+                armour: 2.,
+                resistances: Resistances {
+                    kinetic: 0.75,
+                    acid: 1.5,
+                    ..Resistances::NONE
+                },
+                shield: 6.,
+                shield_regen: None,
+                damage_kind: DamageKind::Kinetic,
+                script: None,
+                projectile: ProjectileType::Laser,
+                pattern: FirePattern::Ring { count: 12 },
+                aim: AimParams {
+                    lead: 0.,
+                    inaccuracy: 0.05,
+                },
+                fire_rate: 0.5,
+                spawn_weight: 1,
+                cost: 3.,
+                enrage: Some(EnrageParams {
+                    low_hp: Some(0.3),
+                    squad_losses: Some(3),
+                    squad_radius: 600.,
+                    time_alive: None,
+                    duration: 5.,
+                    cooldown: 10.,
+                    speed: 1.,
+                    fire_rate: 2.,
+                    damage: 1.5,
+                }),
+                behaviour: Some(BehaviourParams {
+                    orbit_time: (8., 12.),
+                    dive_range: None,
+                    strafe: false,
+                    flee_hp: Some(0.2),
+                    dive_speed: 1.,
+                    strafe_speed: 1.5,
+                    no_target: NoTarget::Patrol,
+                }),
+            },
+            EnemyKind::Gunner => EnemyArchetype {
+                hp: 3.,
+                armour: 0.,
+                resistances: Resistances {
+                    fire: 1.5,
+                    ice: 0.5,
+                    ..Resistances::NONE
+                },
+                shield: 4.,
+                shield_regen: Some(ShieldRegen {
+                    delay: 4.,
+                    rate: 2.,
+                }),
+                damage_kind: DamageKind::Ice,
+                script: None,
+                projectile: ProjectileType::Laser,
+                pattern: FirePattern::Volley {
+                    volleys: 3,
+                    count: 3,
+                    spread: 0.4,
+                    interval: 0.15,
+                },
+                aim: AimParams {
+                    lead: 0.9,
+                    inaccuracy: 0.05,
+                },
+                fire_rate: 0.7,
+                spawn_weight: 2,
+                cost: 2.,
+                enrage: Some(EnrageParams {
+                    low_hp: None,
+                    squad_losses: Some(2),
+                    squad_radius: 400.,
+                    time_alive: Some(25.),
+                    duration: 4.,
+                    cooldown: 6.,
+                    speed: 1.3,
+                    fire_rate: 1.5,
+                    damage: 1.25,
+                }),
+                behaviour: Some(BehaviourParams {
+                    orbit_time: (3., 6.),
+                    dive_range: Some(350.),
+                    strafe: true,
+                    flee_hp: Some(0.3),
+                    dive_speed: 2.,
+                    strafe_speed: 1.8,
+                    no_target: NoTarget::Regroup,
+                }),
+            },
+            EnemyKind::Sentinel => EnemyArchetype {
+                hp: 6.,
+                armour: 3.,
+                resistances: Resistances {
+                    fire: 0.5,
+                    ice: 0.5,
+                    acid: 1.25,
+                    ..Resistances::NONE
+                },
+                shield: 8.,
+                shield_regen: Some(ShieldRegen {
+                    delay: 3.,
+                    rate: 3.,
+                }),
+                damage_kind: DamageKind::Fire,
+                script: None,
+                projectile: ProjectileType::Laser,
+                pattern: FirePattern::BulletMl {
+                    path: "bulletml/sentinel.xml",
+                },
+                aim: AimParams {
+                    lead: 0.,
+                    inaccuracy: 0.,
+                },
+                fire_rate: 1.,
+                spawn_weight: 1,
+                cost: 4.,
+                enrage: None,
+                behaviour: Some(BehaviourParams {
+                    orbit_time: (10., 14.),
+                    dive_range: None,
+                    strafe: false,
+                    flee_hp: None,
+                    dive_speed: 1.,
+                    strafe_speed: 1.,
+                    no_target: NoTarget::Regroup,
+                }),
+            },
+        }
+    }
+}
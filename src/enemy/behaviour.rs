@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{Despawn, Enemy, EnemyKind, Health, Player},
+    GameRng, GameTime, WinSize,
+};
+
+use super::formation::Formation;
+
+// How close counts as having arrived somewhere
+const ARRIVE_DISTANCE: f32 = 20.;
+// Entering gives up on reaching the orbit after this many seconds
+const ENTER_TIMEOUT: f32 = 6.;
+// Longest dive before pulling out
+const DIVE_TIMEOUT: f32 = 3.;
+// Strafing runs pass this far above the player
+const STRAFE_HEIGHT: f32 = 260.;
+// Fleeing enemies are gone once this far past the play-field edge
+const FLEE_MARGIN: f32 = 150.;
+// Celebrating enemies spin this fast, in radians per second
+const CELEBRATE_SPIN: f32 = 6.;
+
+/// When an archetype breaks formation and how it moves while it does.
+#[derive(Clone, Copy)]
+pub struct BehaviourParams {
+    /// Seconds spent orbiting between attacks, picked in this range.
+    pub orbit_time: (f32, f32),
+    /// Dives at the player when it is closer than this.
+    pub dive_range: Option<f32>,
+    /// Strafes across above the player when not diving.
+    pub strafe: bool,
+    /// Leaves the field at or below this share of max hp.
+    pub flee_hp: Option<f32>,
+    /// Speeds while diving, strafing, retreating and fleeing, as multiples
+    /// of the formation speed.
+    pub dive_speed: f32,
+    pub strafe_speed: f32,
+    /// What it does while there is no player to fight.
+    pub no_target: NoTarget,
+}
+
+/// Idle behaviour while the player is dead or not yet spawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoTarget {
+    /// Keep circling the formation orbit.
+    Patrol,
+    /// Gather towards the top middle of the field.
+    Regroup,
+    /// Spin in place.
+    Celebrate,
+}
+
+/// How `enemy_movement_system` moves an enemy in its current state.
+pub enum Movement {
+    /// Follow the formation orbit.
+    Orbit,
+    /// Fly straight at `target`, `speed` times the formation speed.
+    Towards { target: Vec2, speed: f32 },
+    /// Stay put and spin.
+    Spin(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BehaviourState {
+    /// Flying in from the spawn point to the formation orbit.
+    Entering,
+    /// Circling the formation pivot.
+    Orbiting,
+    /// Charging at where the player was.
+    Diving,
+    /// Crossing the field above the player.
+    Strafing,
+    /// Heading back to its place on the orbit.
+    Retreating,
+    /// Leaving the field for good.
+    Fleeing,
+    /// No player to fight, see `NoTarget`.
+    Idle,
+}
+
+/// An enemy's current intent, driven by `enemy_behaviour_system` and carried
+/// out by `enemy_movement_system`.
+#[derive(Component)]
+pub struct EnemyBehaviour {
+    pub state: BehaviourState,
+    /// Where Diving, Strafing and Fleeing head for.
+    pub target: Vec2,
+    in_state: f32,
+    orbit_for: f32,
+    max_hp: f32,
+}
+
+impl EnemyBehaviour {
+    pub fn new(max_hp: f32) -> Self {
+        Self {
+            state: BehaviourState::Entering,
+            target: Vec2::ZERO,
+            in_state: 0.,
+            orbit_for: 0.,
+            max_hp,
+        }
+    }
+
+    fn enter(&mut self, state: BehaviourState, target: Vec2) {
+        self.state = state;
+        self.target = target;
+        self.in_state = 0.;
+    }
+
+    pub fn movement(&self, params: &BehaviourParams) -> Movement {
+        let towards = |speed| Movement::Towards {
+            target: self.target,
+            speed,
+        };
+        match self.state {
+            BehaviourState::Entering | BehaviourState::Orbiting => Movement::Orbit,
+            BehaviourState::Diving => towards(params.dive_speed),
+            BehaviourState::Strafing | BehaviourState::Fleeing => towards(params.strafe_speed),
+            BehaviourState::Retreating => towards(1.),
+            BehaviourState::Idle => match params.no_target {
+                NoTarget::Patrol => Movement::Orbit,
+                NoTarget::Regroup => towards(1.),
+                NoTarget::Celebrate => Movement::Spin(CELEBRATE_SPIN),
+            },
+        }
+    }
+
+    /// Whether the enemy shoots in this state.
+    pub fn fires(&self) -> bool {
+        !matches!(
+            self.state,
+            BehaviourState::Entering | BehaviourState::Fleeing | BehaviourState::Idle
+        )
+    }
+}
+
+type BehaviourQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static EnemyKind,
+        &'static Transform,
+        &'static Formation,
+        &'static Health,
+        &'static mut EnemyBehaviour,
+    ),
+    With<Enemy>,
+>;
+
+pub fn enemy_behaviour_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    win_size: Res<WinSize>,
+    mut rng: ResMut<GameRng>,
+    mut query: BehaviourQuery,
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+) {
+    let dt = game_time.delta_seconds();
+    let player = player_query.get_single().ok().map(|tf| tf.translation.truncate());
+    for (entity, kind, transform, formation, health, mut behaviour) in query.iter_mut() {
+        let params = match kind.archetype().behaviour {
+            Some(params) => params,
+            None => continue,
+        };
+        let position = transform.translation.truncate();
+        behaviour.in_state += dt;
+
+        let wounded = params
+            .flee_hp
+            .is_some_and(|share| health.hp <= behaviour.max_hp * share);
+        if wounded && behaviour.state != BehaviourState::Fleeing {
+            // out the nearest side
+            let x = if position.x < 0. { -1. } else { 1. } * (win_size.w / 2. + FLEE_MARGIN * 2.);
+            behaviour.enter(BehaviourState::Fleeing, Vec2::new(x, position.y));
+            continue;
+        }
+
+        let engaged = matches!(
+            behaviour.state,
+            BehaviourState::Orbiting | BehaviourState::Diving | BehaviourState::Strafing | BehaviourState::Retreating
+        );
+        let player = match player {
+            Some(player) => player,
+            None if engaged => {
+                let regroup = Vec2::new(formation.pivot.0 / 2., win_size.h / 3.);
+                behaviour.enter(BehaviourState::Idle, regroup);
+                continue;
+            }
+            // nothing to aim at; only Entering and Fleeing carry on without a target
+            None => Vec2::ZERO,
+        };
+
+        let arrived = position.distance(behaviour.target) < ARRIVE_DISTANCE;
+        match behaviour.state {
+            BehaviourState::Entering => {
+                if position.distance(formation.orbit_point()) < ARRIVE_DISTANCE
+                    || behaviour.in_state > ENTER_TIMEOUT
+                {
+                    behaviour.enter(BehaviourState::Orbiting, Vec2::ZERO);
+                    behaviour.orbit_for = rng.0.gen_range(params.orbit_time.0..=params.orbit_time.1);
+                }
+            }
+            BehaviourState::Orbiting => {
+                if behaviour.in_state < behaviour.orbit_for {
+                    continue;
+                }
+                if params.dive_range.is_some_and(|range| position.distance(player) < range) {
+                    behaviour.enter(BehaviourState::Diving, player);
+                } else if params.strafe {
+                    let x = if position.x < 0. { 1. } else { -1. } * (win_size.w / 2. - 100.);
+                    let y = (player.y + STRAFE_HEIGHT).min(win_size.h / 2. - 50.);
+                    behaviour.enter(BehaviourState::Strafing, Vec2::new(x, y));
+                } else {
+                    behaviour.in_state = 0.;
+                }
+            }
+            BehaviourState::Diving => {
+                if arrived || behaviour.in_state > DIVE_TIMEOUT {
+                    behaviour.enter(BehaviourState::Retreating, Vec2::ZERO);
+                }
+            }
+            BehaviourState::Strafing => {
+                if arrived {
+                    behaviour.enter(BehaviourState::Retreating, Vec2::ZERO);
+                }
+            }
+            BehaviourState::Retreating => {
+                behaviour.target = formation.orbit_point();
+                if position.distance(behaviour.target) < ARRIVE_DISTANCE {
+                    behaviour.enter(BehaviourState::Orbiting, Vec2::ZERO);
+                    behaviour.orbit_for = rng.0.gen_range(params.orbit_time.0..=params.orbit_time.1);
+                }
+            }
+            BehaviourState::Idle => {
+                // the player is back
+                behaviour.enter(BehaviourState::Retreating, Vec2::ZERO);
+            }
+            BehaviourState::Fleeing => {
+                if position.x.abs() > win_size.w / 2. + FLEE_MARGIN
+                    || position.y.abs() > win_size.h / 2. + FLEE_MARGIN
+                {
+                    commands.entity(entity).insert(Despawn);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,535 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, sprite::Anchor, utils::HashMap};
+use rand::Rng;
+
+use crate::{
+    components::{Armour, DamageKind, Despawn, Enemy, ExplosionToSpawn, Health, Player, SpriteSize},
+    events::{BossDefeated, ProjectileFired},
+    projectile::{self, ProjectileType, Shooter},
+    difficulty::Difficulty,
+    tuning::Tuning,
+    EnemyState, GameRng, GameTextures, GameTime, PlayerState, WinSize, BASE_SPEED, TIME_STEP,
+};
+
+use super::{
+    aim::{lead_direction, AimParams},
+    enemy_laser_damage, enemy_projectile,
+    pattern::{Emitter, FirePattern},
+};
+
+// Bosses hold station this far below the top of the field
+const TOP_MARGIN: f32 = 170.;
+// Pixels per second while flying in
+const ENTER_SPEED: f32 = 120.;
+// Pixels per second when catching up with its movement pattern
+const CATCH_UP_SPEED: f32 = BASE_SPEED;
+// Length of the death sequence, and how often it throws out an explosion
+const DEATH_DURATION: f32 = 2.;
+const DEATH_BLAST_INTERVAL: f32 = 0.08;
+// Damage armour parts take off each hit
+const ARMOUR_PLATING: f32 = 4.;
+const BODY_Z: f32 = 9.;
+const PART_Z: f32 = 10.;
+const BAR_SIZE: (f32, f32) = (600., 14.);
+const BAR_Z: f32 = 50.;
+
+const BODY_TINT: Color = Color::rgb(0.55, 0.55, 0.7);
+const TURRET_TINT: Color = Color::rgb(1., 0.85, 0.5);
+const WEAK_POINT_TINT: Color = Color::rgb(1., 0.3, 0.3);
+const ARMOUR_TINT: Color = Color::rgb(0.45, 0.45, 0.45);
+const BAR_BACK: Color = Color::rgba(0.2, 0.2, 0.2, 0.8);
+const BAR_FILL: Color = Color::rgb(0.9, 0.15, 0.15);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BossKind {
+    Mothership,
+    Fortress,
+}
+
+impl BossKind {
+    pub const ALL: [BossKind; 2] = [BossKind::Mothership, BossKind::Fortress];
+
+    /// Bosses take turns, one per wave.
+    pub fn for_wave(wave: u32) -> Self {
+        Self::ALL[(wave.max(1) - 1) as usize % Self::ALL.len()]
+    }
+
+    pub fn def(&self) -> &'static BossDef {
+        match self {
+            BossKind::Mothership => &MOTHERSHIP,
+            BossKind::Fortress => &FORTRESS,
+        }
+    }
+}
+
+/// A boss's layout and how it fights.
+pub struct BossDef {
+    /// Size of the body sprite, which itself can't be hit.
+    pub size: (f32, f32),
+    pub parts: &'static [PartDef],
+    /// In order, each taking over as the weak points drop below its `below`.
+    pub phases: &'static [PhaseDef],
+    pub aim: AimParams,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartKind {
+    /// Shoots the current phase's pattern until destroyed.
+    Turret,
+    /// The boss dies once every weak point is destroyed.
+    WeakPoint,
+    /// Soaks up shots aimed at whatever is behind it.
+    Armour,
+}
+
+pub struct PartDef {
+    pub kind: PartKind,
+    /// From the body's centre.
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+    pub hp: f32,
+}
+
+pub struct PhaseDef {
+    /// Share of the weak points' total hp at or below which the phase starts.
+    pub below: f32,
+    pub projectile: ProjectileType,
+    pub pattern: FirePattern,
+    /// Salvos per `enemy_fire_cooldown`, per turret.
+    pub fire_rate: f32,
+    pub movement: BossMovement,
+}
+
+/// Path around the boss's station, repeating.
+#[derive(Clone, Copy)]
+pub enum BossMovement {
+    /// Bob gently in place.
+    Hover,
+    /// Side to side across `width` pixels every `period` seconds.
+    Sweep { width: f32, period: f32 },
+    /// A figure of eight `width` by `height` pixels every `period` seconds.
+    Figure8 { width: f32, height: f32, period: f32 },
+}
+
+impl BossMovement {
+    fn offset(&self, t: f32) -> Vec2 {
+        match *self {
+            BossMovement::Hover => Vec2::new(0., 10. * (t * TAU / 3.).sin()),
+            BossMovement::Sweep { width, period } => Vec2::new(width / 2. * (t * TAU / period).sin(), 0.),
+            BossMovement::Figure8 { width, height, period } => Vec2::new(
+                width / 2. * (t * TAU / period).sin(),
+                height / 2. * (2. * t * TAU / period).sin(),
+            ),
+        }
+    }
+}
+
+static MOTHERSHIP: BossDef = BossDef {
+    size: (420., 160.),
+    parts: &[
+        PartDef { kind: PartKind::WeakPoint, offset: (0., 0.), size: (60., 60.), hp: 100. },
+        PartDef { kind: PartKind::Turret, offset: (-150., -30.), size: (50., 50.), hp: 40. },
+        PartDef { kind: PartKind::Turret, offset: (150., -30.), size: (50., 50.), hp: 40. },
+        PartDef { kind: PartKind::Armour, offset: (-55., -70.), size: (100., 24.), hp: 60. },
+        PartDef { kind: PartKind::Armour, offset: (55., -70.), size: (100., 24.), hp: 60. },
+    ],
+    phases: &[
+        PhaseDef {
+            below: 1.,
+            projectile: ProjectileType::Laser,
+            pattern: FirePattern::Burst { count: 3, spread: 0.4 },
+            fire_rate: 0.8,
+            movement: BossMovement::Sweep { width: 300., period: 8. },
+        },
+        PhaseDef {
+            below: 0.6,
+            projectile: ProjectileType::Laser,
+            pattern: FirePattern::Volley { volleys: 3, count: 3, spread: 0.5, interval: 0.2 },
+            fire_rate: 1.,
+            movement: BossMovement::Sweep { width: 500., period: 5. },
+        },
+        PhaseDef {
+            below: 0.3,
+            projectile: ProjectileType::Spinning,
+            pattern: FirePattern::Spiral { arms: 4, angular_speed: 2. },
+            fire_rate: 3.,
+            movement: BossMovement::Figure8 { width: 500., height: 120., period: 6. },
+        },
+    ],
+    aim: AimParams { lead: 0.6, inaccuracy: 0.05 },
+};
+
+static FORTRESS: BossDef = BossDef {
+    size: (520., 200.),
+    parts: &[
+        PartDef { kind: PartKind::WeakPoint, offset: (-110., 20.), size: (50., 50.), hp: 80. },
+        PartDef { kind: PartKind::WeakPoint, offset: (110., 20.), size: (50., 50.), hp: 80. },
+        PartDef { kind: PartKind::Turret, offset: (-210., -40.), size: (50., 50.), hp: 50. },
+        PartDef { kind: PartKind::Turret, offset: (-70., -60.), size: (50., 50.), hp: 50. },
+        PartDef { kind: PartKind::Turret, offset: (70., -60.), size: (50., 50.), hp: 50. },
+        PartDef { kind: PartKind::Turret, offset: (210., -40.), size: (50., 50.), hp: 50. },
+        PartDef { kind: PartKind::Armour, offset: (-110., -20.), size: (90., 24.), hp: 80. },
+        PartDef { kind: PartKind::Armour, offset: (110., -20.), size: (90., 24.), hp: 80. },
+    ],
+    phases: &[
+        PhaseDef {
+            below: 1.,
+            projectile: ProjectileType::Laser,
+            pattern: FirePattern::Ring { count: 8 },
+            fire_rate: 0.5,
+            movement: BossMovement::Hover,
+        },
+        PhaseDef {
+            below: 0.5,
+            projectile: ProjectileType::Homing,
+            pattern: FirePattern::Aimed,
+            fire_rate: 0.7,
+            movement: BossMovement::Sweep { width: 250., period: 10. },
+        },
+        PhaseDef {
+            below: 0.2,
+            projectile: ProjectileType::Sine,
+            pattern: FirePattern::Burst { count: 5, spread: 1. },
+            fire_rate: 1.2,
+            movement: BossMovement::Figure8 { width: 300., height: 80., period: 8. },
+        },
+    ],
+    aim: AimParams { lead: 0.3, inaccuracy: 0.1 },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BossStage {
+    /// Flying down to its station, parts can already be shot.
+    Entering,
+    Fighting,
+    /// Blowing up, see `DEATH_DURATION`.
+    Dying,
+}
+
+/// The boss body. Its parts are separate entities that follow it around.
+#[derive(Component)]
+pub struct Boss {
+    pub kind: BossKind,
+    pub stage: BossStage,
+    /// Index into the def's phases.
+    pub phase: usize,
+    clock: f32,
+    /// Total hp of the weak points at spawn.
+    max_hp: f32,
+    dying: Timer,
+    blast: Timer,
+}
+
+/// A piece of a boss that can be shot, placed at `offset` from the body.
+#[derive(Component)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub kind: PartKind,
+    offset: Vec2,
+}
+
+/// Backing of a boss's health bar, the fill is its only child.
+#[derive(Component)]
+pub struct BossHealthBar {
+    boss: Entity,
+}
+
+#[derive(Component)]
+pub struct BossHealthBarFill;
+
+pub fn spawn_boss(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    tuning: &Tuning,
+    difficulty: &Difficulty,
+    win_size: &WinSize,
+    kind: BossKind,
+) -> Entity {
+    let def = kind.def();
+    let first = &def.phases[0];
+    // come in from above the field
+    let start = Vec2::new(0., win_size.h / 2. + def.size.1);
+    let boss = commands
+        .spawn_bundle(SpriteBundle {
+            texture: game_textures.enemy.clone(),
+            sprite: Sprite {
+                color: BODY_TINT,
+                custom_size: Some(Vec2::from(def.size)),
+                ..default()
+            },
+            transform: Transform::from_translation(start.extend(BODY_Z)),
+            ..default()
+        })
+        .id();
+
+    let mut max_hp = 0.;
+    for part in def.parts {
+        let hp = part.hp * tuning.enemy_hp_multiplier * difficulty.hp();
+        let offset = Vec2::from(part.offset);
+        let color = match part.kind {
+            PartKind::Turret => TURRET_TINT,
+            PartKind::WeakPoint => {
+                max_hp += hp;
+                WEAK_POINT_TINT
+            }
+            PartKind::Armour => ARMOUR_TINT,
+        };
+        let mut entity = commands.spawn_bundle(SpriteBundle {
+            texture: game_textures.enemy.clone(),
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::from(part.size)),
+                ..default()
+            },
+            transform: Transform::from_translation((start + offset).extend(PART_Z)),
+            ..default()
+        });
+        entity
+            .insert(Enemy)
+            .insert(BossPart { boss, kind: part.kind, offset })
+            .insert(SpriteSize::from(part.size))
+            .insert(Health { hp, multiplier: 1. });
+        if part.kind == PartKind::Armour {
+            entity.insert(Armour(ARMOUR_PLATING));
+        }
+        if part.kind == PartKind::Turret {
+            entity
+                .insert(EnemyState::new(tuning, first.fire_rate))
+                .insert(Emitter::new(first.pattern));
+        }
+    }
+
+    commands.entity(boss).insert(Boss {
+        kind,
+        stage: BossStage::Entering,
+        phase: 0,
+        clock: 0.,
+        max_hp,
+        dying: Timer::from_seconds(DEATH_DURATION, false),
+        blast: Timer::from_seconds(DEATH_BLAST_INTERVAL, true),
+    });
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: BAR_BACK,
+                custom_size: Some(Vec2::from(BAR_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., win_size.h / 2. - 25., BAR_Z),
+            ..default()
+        })
+        .insert(BossHealthBar { boss })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: BAR_FILL,
+                        custom_size: Some(Vec2::from(BAR_SIZE)),
+                        anchor: Anchor::CenterLeft,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(-BAR_SIZE.0 / 2., 0., 0.1),
+                    ..default()
+                })
+                .insert(BossHealthBarFill);
+        });
+    boss
+}
+
+pub fn boss_movement_system(
+    game_time: Res<GameTime>,
+    win_size: Res<WinSize>,
+    mut boss_query: Query<(&mut Boss, &mut Transform)>,
+    mut part_query: Query<(&BossPart, &mut Transform), Without<Boss>>,
+) {
+    let dt = game_time.delta_seconds();
+    let station = Vec2::new(0., win_size.h / 2. - TOP_MARGIN);
+    for (mut boss, mut transform) in boss_query.iter_mut() {
+        let position = transform.translation.truncate();
+        let moved = match boss.stage {
+            BossStage::Entering => {
+                if position.distance(station) < 1. {
+                    boss.stage = BossStage::Fighting;
+                    boss.clock = 0.;
+                }
+                (station - position).clamp_length_max(ENTER_SPEED * dt)
+            }
+            BossStage::Fighting => {
+                boss.clock += dt;
+                let target = station + boss.kind.def().phases[boss.phase].movement.offset(boss.clock);
+                (target - position).clamp_length_max(CATCH_UP_SPEED * dt)
+            }
+            BossStage::Dying => Vec2::ZERO,
+        };
+        transform.translation += moved.extend(0.);
+    }
+
+    for (part, mut transform) in part_query.iter_mut() {
+        if let Ok((_, boss_transform)) = boss_query.get(part.boss) {
+            let position = boss_transform.translation.truncate() + part.offset;
+            (transform.translation.x, transform.translation.y) = (position.x, position.y);
+        }
+    }
+}
+
+type BossPartQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static BossPart,
+        &'static Health,
+        &'static Transform,
+        Option<&'static mut Emitter>,
+        Option<&'static mut EnemyState>,
+    ),
+>;
+type HealthBarFill = (With<BossHealthBarFill>, Without<Boss>, Without<BossPart>);
+
+/// Tracks the weak points: switches phases, fills the health bar, and runs
+/// the death sequence once they are all gone.
+#[allow(clippy::too_many_arguments)]
+pub fn boss_phase_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    tuning: Res<Tuning>,
+    mut rng: ResMut<GameRng>,
+    mut boss_query: Query<(Entity, &mut Boss, &Transform)>,
+    mut part_query: BossPartQuery,
+    bar_query: Query<(Entity, &BossHealthBar, &Children)>,
+    mut fill_query: Query<&mut Transform, HealthBarFill>,
+    mut defeated_events: EventWriter<BossDefeated>,
+) {
+    let mut weak_hp: HashMap<Entity, f32> = HashMap::default();
+    for (_, part, health, ..) in part_query.iter() {
+        if part.kind == PartKind::WeakPoint {
+            *weak_hp.entry(part.boss).or_insert(0.) += health.hp.max(0.);
+        }
+    }
+
+    for (entity, mut boss, transform) in boss_query.iter_mut() {
+        let def = boss.kind.def();
+        let hp = weak_hp.get(&entity).copied().unwrap_or(0.);
+        match boss.stage {
+            BossStage::Entering | BossStage::Fighting if hp <= 0. => {
+                boss.stage = BossStage::Dying;
+                // whatever is left of it goes up with the first blasts
+                for (part_entity, part, _, part_transform, ..) in part_query.iter() {
+                    if part.boss == entity {
+                        commands.entity(part_entity).insert(Despawn);
+                        commands.spawn().insert(ExplosionToSpawn(part_transform.translation));
+                    }
+                }
+            }
+            BossStage::Entering | BossStage::Fighting => {
+                let share = hp / boss.max_hp.max(f32::EPSILON);
+                let phase = def.phases.iter().rposition(|phase| share <= phase.below).unwrap_or(0);
+                if phase != boss.phase {
+                    boss.phase = phase;
+                    boss.clock = 0.;
+                    let phase = &def.phases[phase];
+                    for (_, part, _, _, emitter, state) in part_query.iter_mut() {
+                        if let (true, Some(mut emitter), Some(mut state)) = (part.boss == entity, emitter, state) {
+                            *emitter = Emitter::new(phase.pattern);
+                            *state = EnemyState::new(&tuning, phase.fire_rate);
+                        }
+                    }
+                }
+            }
+            BossStage::Dying => {
+                if boss.blast.tick(game_time.delta()).just_finished() {
+                    let half = Vec2::from(def.size) / 2.;
+                    let at = transform.translation.truncate()
+                        + Vec2::new(rng.0.gen_range(-half.x..=half.x), rng.0.gen_range(-half.y..=half.y));
+                    commands.spawn().insert(ExplosionToSpawn(at.extend(PART_Z + 1.)));
+                }
+                if boss.dying.tick(game_time.delta()).finished() {
+                    commands.entity(entity).insert(Despawn);
+                    defeated_events.send(BossDefeated {
+                        entity,
+                        position: transform.translation,
+                    });
+                }
+            }
+        }
+    }
+
+    for (bar, health_bar, children) in bar_query.iter() {
+        let share = match boss_query.get(health_bar.boss) {
+            Ok((_, boss, _)) if boss.stage != BossStage::Dying => {
+                weak_hp.get(&health_bar.boss).copied().unwrap_or(0.) / boss.max_hp.max(f32::EPSILON)
+            }
+            _ => {
+                commands.entity(bar).insert(Despawn);
+                continue;
+            }
+        };
+        for &child in children.iter() {
+            if let Ok(mut fill) = fill_query.get_mut(child) {
+                fill.scale.x = share.clamp(0., 1.);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn boss_fire_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    game_textures: Res<GameTextures>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    player_state: Res<PlayerState>,
+    mut rng: ResMut<GameRng>,
+    boss_query: Query<&Boss>,
+    mut turret_query: Query<(Entity, &Transform, &BossPart, &mut EnemyState, &mut Emitter)>,
+    player_query: Query<&Transform, (With<Player>, Without<BossPart>)>,
+    mut fired_events: EventWriter<ProjectileFired>,
+) {
+    // nobody to shoot at
+    let player_transform = match player_query.get_single() {
+        Ok(player_transform) => player_transform,
+        Err(_) => return,
+    };
+    let player_velocity = Vec2::new(player_state.delta_x, player_state.delta_y) / TIME_STEP;
+    for (entity, transform, part, mut state, mut emitter) in turret_query.iter_mut() {
+        let boss = match boss_query.get(part.boss) {
+            Ok(boss) if boss.stage == BossStage::Fighting => boss,
+            _ => continue,
+        };
+        let def = boss.kind.def();
+        let projectile = enemy_projectile(def.phases[boss.phase].projectile, &difficulty);
+        let delta = game_time.delta().mul_f32(difficulty.fire_rate());
+        let triggered = state.fire_cooldown.tick(delta).finished();
+        if triggered {
+            state.fire_cooldown.reset();
+        }
+        let aim = lead_direction(
+            &def.aim,
+            tuning.enemy_aim_skill,
+            transform.translation.truncate(),
+            player_transform.translation.truncate(),
+            player_velocity,
+            projectile.speed * BASE_SPEED,
+            &mut rng.0,
+        );
+        let aim = if aim == Vec2::ZERO { Vec2::NEG_Y } else { aim };
+        for direction in emitter.update(delta, triggered, aim) {
+            projectile::fire(
+                &mut commands,
+                &game_textures,
+                &projectile,
+                Shooter::Enemy(entity),
+                transform.translation,
+                direction,
+                enemy_laser_damage(&difficulty, &tuning, DamageKind::Kinetic),
+            );
+            fired_events.send(ProjectileFired {
+                from_player: false,
+                position: transform.translation,
+            });
+        }
+    }
+}
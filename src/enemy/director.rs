@@ -0,0 +1,209 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{Despawn, EnemyKind, Health, Player},
+    difficulty::Difficulty,
+    events::{EnemyKilled, EnemySpawned},
+    tuning::Tuning,
+    GameRng, GameTextures, GameTime, WinSize, SPRITE_SCALE,
+};
+
+use super::{
+    formation::{Formation, FormationMaker},
+    population::EnemyPopulation,
+    spawn_enemy,
+    wave::Waves,
+};
+
+// Seconds for the budget rate to double
+const RAMP_SECONDS: f32 = 60.;
+// Window over which the player's kill pace is measured
+const RECENT_SECONDS: f32 = 10.;
+// Kills per second that earn the full performance bonus
+const FAST_KILL_PACE: f32 = 0.5;
+// Unspent budget is capped at this many times the threat limit
+const BUDGET_BANK: f32 = 2.;
+// Extra threat allowed per wave after the first
+const THREAT_PER_WAVE: f32 = 2.;
+// How long a spawn point flashes before the enemy comes in
+const TELEGRAPH_SECONDS: f32 = 0.8;
+// Telegraphs sit this far inside the field edge, where they can be seen
+const TELEGRAPH_INSET: f32 = 40.;
+const TELEGRAPH_Z: f32 = 20.;
+
+/// Decides what to spawn: a budget grows over time and with how well the
+/// player is doing, and gets spent on archetypes by their `cost` as long as
+/// the enemies on the field stay under the threat limit.
+pub struct Director {
+    pub budget: f32,
+    /// Threat on the field and on its way, as of the last decision.
+    pub threat: f32,
+    pub max_threat: f32,
+    elapsed: f32,
+    recent_kills: Vec<f32>,
+    tick: Timer,
+}
+
+impl Director {
+    pub fn new(tuning: &Tuning) -> Self {
+        Self {
+            budget: 0.,
+            threat: 0.,
+            max_threat: tuning.director_max_threat,
+            elapsed: 0.,
+            recent_kills: Vec::new(),
+            tick: Timer::from_seconds(tuning.enemy_spawn_interval.max(0.01), true),
+        }
+    }
+
+    /// 0.5 for a player hanging on by a thread, up to 1.5 for one at full
+    /// health mowing enemies down.
+    fn performance(&self, health: &Health, tuning: &Tuning) -> f32 {
+        let health = (health.hp / tuning.player_hp.max(f32::EPSILON)).clamp(0., 1.);
+        let pace = self.recent_kills.len() as f32 / RECENT_SECONDS / FAST_KILL_PACE;
+        (0.5 + 0.5 * health) * (1. + 0.5 * pace.min(1.))
+    }
+}
+
+/// A spawn point flashing before `kind` comes in on `formation`.
+#[derive(Component)]
+pub struct SpawnTelegraph {
+    pub kind: EnemyKind,
+    formation: Formation,
+    timer: Timer,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn director_system(
+    mut commands: Commands,
+    mut director: ResMut<Director>,
+    game_time: Res<GameTime>,
+    game_textures: Res<GameTextures>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    population: Res<EnemyPopulation>,
+    waves: Res<Waves>,
+    win_size: Res<WinSize>,
+    mut formation_maker: ResMut<FormationMaker>,
+    mut rng: ResMut<GameRng>,
+    mut killed_events: EventReader<EnemyKilled>,
+    telegraph_query: Query<&SpawnTelegraph>,
+    player_query: Query<&Health, With<Player>>,
+) {
+    let dt = game_time.delta_seconds();
+    director.elapsed += dt;
+    let now = director.elapsed;
+    for _ in killed_events.iter() {
+        director.recent_kills.push(now);
+    }
+    director.recent_kills.retain(|at| now - at < RECENT_SECONDS);
+
+    director.max_threat = tuning.director_max_threat + (waves.number - 1) as f32 * THREAT_PER_WAVE;
+    // nothing builds up while the player is down
+    if let Ok(health) = player_query.get_single() {
+        let rate = tuning.director_budget_rate
+            * (1. + director.elapsed / RAMP_SECONDS)
+            * director.performance(health, &tuning)
+            * difficulty.spawn_rate();
+        director.budget = (director.budget + rate * dt).min(director.max_threat * BUDGET_BANK);
+    }
+
+    let on_field: f32 = population
+        .iter()
+        .map(|(kind, count)| kind.archetype().cost * *count as f32)
+        .sum();
+    let incoming: f32 = telegraph_query.iter().map(|telegraph| telegraph.kind.archetype().cost).sum();
+    director.threat = on_field + incoming;
+
+    if !director.tick.tick(game_time.delta()).just_finished() || !waves.spawning() {
+        return;
+    }
+    let room = (director.max_threat - director.threat).min(director.budget);
+    let kind = match pick_kind(&mut rng.0, room) {
+        Some(kind) => kind,
+        // save up for something bigger
+        None => return,
+    };
+    director.budget -= kind.archetype().cost;
+    director.threat += kind.archetype().cost;
+
+    let formation = formation_maker.make(&win_size, &mut rng.0);
+    let edge = Vec2::new(win_size.w / 2., win_size.h / 2.) - TELEGRAPH_INSET;
+    let marker = Vec2::from(formation.start).clamp(-edge, edge);
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: game_textures.enemy.clone(),
+            sprite: Sprite {
+                color: Color::rgba(1., 1., 1., 0.),
+                ..default()
+            },
+            transform: Transform {
+                translation: marker.extend(TELEGRAPH_Z),
+                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.) * 0.6,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SpawnTelegraph {
+            kind,
+            formation,
+            timer: Timer::from_seconds(TELEGRAPH_SECONDS, false),
+        });
+}
+
+/// Weighted pick over the archetypes' `spawn_weight`, among those costing
+/// at most `affordable`.
+fn pick_kind(rng: &mut impl Rng, affordable: f32) -> Option<EnemyKind> {
+    let candidates: Vec<EnemyKind> = EnemyKind::ALL
+        .into_iter()
+        .filter(|kind| kind.archetype().cost <= affordable)
+        .collect();
+    let total: u32 = candidates.iter().map(|kind| kind.archetype().spawn_weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0..total);
+    for kind in candidates {
+        let weight = kind.archetype().spawn_weight;
+        if roll < weight {
+            return Some(kind);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+/// Flashes spawn points, then brings the enemy in. Telegraphs still pending
+/// when the wave stops spawning are dropped.
+#[allow(clippy::too_many_arguments)]
+pub fn telegraph_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    game_textures: Res<GameTextures>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    waves: Res<Waves>,
+    mut spawned_events: EventWriter<EnemySpawned>,
+    mut query: Query<(Entity, &mut SpawnTelegraph, &mut Sprite)>,
+) {
+    for (entity, mut telegraph, mut sprite) in query.iter_mut() {
+        if !telegraph.timer.tick(game_time.delta()).finished() {
+            let blink = (telegraph.timer.elapsed_secs() * TAU * 3.).sin().abs();
+            sprite.color.set_a(0.2 + 0.6 * blink);
+            continue;
+        }
+        commands.entity(entity).insert(Despawn);
+        if !waves.spawning() {
+            continue;
+        }
+        let (x, y) = telegraph.formation.start;
+        let enemy = spawn_enemy(&mut commands, &game_textures, &tuning, &difficulty, telegraph.kind, telegraph.formation.clone());
+        spawned_events.send(EnemySpawned {
+            entity: enemy,
+            position: Vec3::new(x, y, 10.),
+        });
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{Damage, Enemy, EnemyKind, Health},
+    events::EnemyKilled,
+    GameTime,
+};
+
+const ENRAGED_TINT: Color = Color::rgb(1., 0.35, 0.35);
+
+/// When an archetype enrages and what that does to it. Any one trigger is
+/// enough, `None` turns a trigger off.
+#[derive(Clone, Copy)]
+pub struct EnrageParams {
+    /// Share of max hp at or below which the enemy enrages.
+    pub low_hp: Option<f32>,
+    /// Allies killed within `squad_radius` since the last enrage.
+    pub squad_losses: Option<u32>,
+    pub squad_radius: f32,
+    /// Seconds alive.
+    pub time_alive: Option<f32>,
+    /// Seconds spent enraged.
+    pub duration: f32,
+    /// Seconds calm before it can enrage again.
+    pub cooldown: f32,
+    pub speed: f32,
+    pub fire_rate: f32,
+    pub damage: f32,
+}
+
+/// Enrage bookkeeping, plus the multipliers currently in effect (1 when calm).
+#[derive(Component)]
+pub struct Rage {
+    max_hp: f32,
+    alive: f32,
+    squad_losses: u32,
+    enraged: Option<Timer>,
+    cooldown: Timer,
+    pub speed: f32,
+    pub fire_rate: f32,
+    pub damage: f32,
+}
+
+impl Rage {
+    pub fn new(max_hp: f32, params: &EnrageParams) -> Self {
+        let mut cooldown = Timer::from_seconds(params.cooldown, false);
+        // free to enrage from the start
+        cooldown.set_elapsed(cooldown.duration());
+        Self {
+            max_hp,
+            alive: 0.,
+            squad_losses: 0,
+            enraged: None,
+            cooldown,
+            speed: 1.,
+            fire_rate: 1.,
+            damage: 1.,
+        }
+    }
+
+    pub fn is_enraged(&self) -> bool {
+        self.enraged.is_some()
+    }
+
+    fn triggered(&self, params: &EnrageParams, hp: f32) -> bool {
+        params.low_hp.is_some_and(|share| hp <= self.max_hp * share)
+            || params.squad_losses.is_some_and(|losses| self.squad_losses >= losses)
+            || params.time_alive.is_some_and(|seconds| self.alive >= seconds)
+    }
+}
+
+/// Damage of a shot fired by an enemy in the given rage state.
+pub fn enraged_damage(mut damage: Damage, rage: Option<&Rage>) -> Damage {
+    if let Some(rage) = rage {
+        damage.multiplier *= rage.damage;
+    }
+    damage
+}
+
+type EnrageQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static EnemyKind, &'static Transform, &'static Health, &'static mut Rage, &'static mut Sprite),
+    With<Enemy>,
+>;
+
+pub fn enemy_enrage_system(
+    game_time: Res<GameTime>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut query: EnrageQuery,
+) {
+    let kills: Vec<(Entity, Vec2)> = killed_events
+        .iter()
+        .map(|event| (event.entity, event.position.truncate()))
+        .collect();
+    for (entity, kind, transform, health, mut rage, mut sprite) in query.iter_mut() {
+        let params = match kind.archetype().enrage {
+            Some(params) => params,
+            None => continue,
+        };
+        let position = transform.translation.truncate();
+        rage.alive += game_time.delta_seconds();
+        rage.squad_losses += kills
+            .iter()
+            .filter(|(killed, at)| *killed != entity && at.distance(position) <= params.squad_radius)
+            .count() as u32;
+
+        if let Some(timer) = &mut rage.enraged {
+            if timer.tick(game_time.delta()).finished() {
+                rage.enraged = None;
+                rage.cooldown.reset();
+                rage.squad_losses = 0;
+                (rage.speed, rage.fire_rate, rage.damage) = (1., 1., 1.);
+                sprite.color = Color::WHITE;
+            } else {
+                // status effects tint over this and reset it when they wear off
+                sprite.color = ENRAGED_TINT;
+            }
+        } else if rage.cooldown.tick(game_time.delta()).finished() && rage.triggered(&params, health.hp) {
+            rage.enraged = Some(Timer::from_seconds(params.duration, false));
+            rage.squad_losses = 0;
+            (rage.speed, rage.fire_rate, rage.damage) = (params.speed, params.fire_rate, params.damage);
+            sprite.color = ENRAGED_TINT;
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use bevy::prelude::Component;
-use rand::{thread_rng, Rng};
+use bevy::prelude::{Component, Vec2};
+use rand::Rng;
 use crate::{BASE_SPEED, WinSize, FORMATION_MEMBERS_MAX};
 
 #[derive(Clone,Component)]
@@ -11,6 +11,27 @@ pub struct Formation {
     pub angle: f32,
 }
 
+impl Formation {
+    /// Formation orbiting around, and starting from, a fixed point.
+    pub fn at(x: f32, y: f32) -> Self {
+        Formation {
+            start: (x, y),
+            pivot: (x, y),
+            radius: (175., 100.),
+            angle: 0.,
+            speed: BASE_SPEED / 2.,
+        }
+    }
+
+    /// Where on its orbit the formation currently is.
+    pub fn orbit_point(&self) -> Vec2 {
+        Vec2::new(
+            self.radius.0 * self.angle.cos() + self.pivot.0,
+            self.radius.1 * self.angle.sin() + self.pivot.1,
+        )
+    }
+}
+
 #[derive(Default)]
 pub struct FormationMaker {
     current_template: Option<Formation>,
@@ -19,18 +40,17 @@ pub struct FormationMaker {
 }
 
 impl FormationMaker {
-    pub fn make(&mut self, win_size: &WinSize) -> Formation {
+    pub fn make(&mut self, win_size: &WinSize, rng: &mut impl Rng) -> Formation {
         match (&self.current_template, self.current_members >= FORMATION_MEMBERS_MAX) {
             (Some(tmpl), false) => {
                 self.current_members += 1;
                 tmpl.clone()
             }
             (None, _) | (_, true) => {
-                let mut rng = thread_rng();
                 let w_span = win_size.w/2. + 100.;
                 let h_span = win_size.h/2. + 100.;
                 let x = if rng.gen_bool(0.5) {w_span} else {-w_span};
-                let y = rng.gen_range(-h_span..h_span) as f32;
+                let y = rng.gen_range(-h_span..h_span);
 
                 let start = (x,y);
 
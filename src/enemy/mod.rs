@@ -1,62 +1,157 @@
 use std::f32::consts::PI;
 
 use crate::{
-    components::{Enemy, FromEnemy, Laser, Movable, SpriteSize, Velocity, Health, Damage, NumberOfHits, ParentEntity, Player},
-    EnemyCount, GameTextures, WinSize, ENEMY_LASER_SIZE, ENEMY_MAX, ENEMY_SIZE, SPRITE_SCALE, BASE_SPEED, TIME_STEP, EnemyState, PlayerState,
+    components::{Enemy, EnemyKind, FromEnemy, Laser, Movable, SpriteSize, Velocity, Health, Damage, DamageKind, Armour, NumberOfHits, ParentEntity, Player},
+    shield::Shield,
+    status::StatusEffects,
+    GameTextures, WinSize, ENEMY_LASER_SIZE, ENEMY_SIZE, SPRITE_SCALE, BASE_SPEED, TIME_STEP, EnemyState, PlayerState,
+    GameTime, events::{EnemySpawned, ProjectileFired},
+    console::{parse_arg, ConsoleAppExt},
+    bulletml::{self, BulletMlDocs, BulletMlEmitter, Context, Runner},
+    projectile::{self, ProjectileDef, ProjectileType, Shooter},
+    tuning::Tuning, GameRng,
+    difficulty::Difficulty,
 };
-use bevy::{time::FixedTimestep, ecs::schedule::ShouldRun, prelude::*, math::Vec3Swizzles};
+use bevy::{ecs::system::CommandQueue, time::FixedTimestep, ecs::schedule::ShouldRun, prelude::*, math::Vec3Swizzles};
 use rand::{thread_rng, Rng};
 
-use self::formation::{FormationMaker, Formation};
+use self::aim::lead_direction;
+use self::behaviour::{EnemyBehaviour, Movement};
+use self::formation::FormationMaker;
+use self::enrage::{enraged_damage, Rage};
+use self::pattern::{Emitter, FirePattern};
+use self::script::{EnemyScript, EnemyScriptAsset, EnemyScriptLoader, EnemyScripts};
+pub use self::boss::{Boss, BossHealthBar, BossPart};
+pub use self::director::{Director, SpawnTelegraph};
+pub use self::formation::Formation;
+pub use self::population::EnemyPopulation;
+pub use self::wave::Waves;
 
+mod aim;
+mod archetype;
+mod behaviour;
+mod boss;
+mod director;
+mod enrage;
 mod formation;
+mod pattern;
+mod population;
+mod script;
+mod wave;
+
+/// Enemies left to the built-in movement and firing.
+type BuiltInEnemy = (With<Enemy>, Without<EnemyScript>);
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
+        let tuning = app.world.get_resource::<Tuning>().cloned().unwrap_or_default();
         app.insert_resource(FormationMaker::default())
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(1.5))
-                .with_system(enemy_spawn_system),
-        )
+        .insert_resource(Director::new(&tuning))
+        .insert_resource(EnemyPopulation::default())
+        .add_asset::<EnemyScriptAsset>()
+        .init_asset_loader::<EnemyScriptLoader>()
+        .insert_resource(EnemyScripts::default())
+        .insert_resource(Waves::default())
+        .add_system_to_stage(CoreStage::PreUpdate, population::enemy_population_system)
+        .add_system(director::director_system)
+        .add_system(director::telegraph_system)
+        .add_system(wave::wave_system)
+        .add_system(boss::boss_movement_system)
+        .add_system(boss::boss_phase_system)
+        .add_system(boss::boss_fire_system)
+        .add_system(behaviour::enemy_behaviour_system.before(enemy_movement_system))
         .add_system(enemy_movement_system)
-        .add_system(enemy_fire_system);
+        .register_console_command("spawn enemy", "spawn enemy <archetype> <x> <y>", spawn_enemy_command)
+        .register_console_command("wave", "wave [<number>|boss]", wave::wave_command)
+        .add_system(enemy_fire_system)
+        .add_system(enrage::enemy_enrage_system)
+        .add_system(script::scripted_enemy_system);
     }
 }
 
-fn enemy_spawn_system(
-    mut commands: Commands,
-    game_textures: Res<GameTextures>,
-    mut enemy_count: ResMut<EnemyCount>,
-    mut formation_maker: ResMut<FormationMaker>,
-    win_size: Res<WinSize>,
-) {
-    if enemy_count.0 < ENEMY_MAX {
-        let formation = formation_maker.make(&win_size);
-        let (x,y) = formation.start;
-
-        commands
-            .spawn_bundle(SpriteBundle {
-                texture: game_textures.enemy.clone(),
-                transform: Transform {
-                    scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
-                    translation: Vec3::new(x, y, 10.),
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert(Enemy)
-            .insert(EnemyState::default())
-            .insert(formation)
-            .insert(SpriteSize::from(ENEMY_SIZE))
-            .insert(Health {hp: 2., multiplier: 0.})
-            .insert(Velocity{x:0.,y:0.});
-            // .insert(LastFired { time:-1., rate: 1.})
-            // .insert(NumberOfHits{hits:0});
-        enemy_count.0 += 1;
+fn spawn_enemy(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    tuning: &Tuning,
+    difficulty: &Difficulty,
+    kind: EnemyKind,
+    formation: Formation,
+) -> Entity {
+    let (x,y) = formation.start;
+    let archetype = kind.archetype();
+    let hp = archetype.hp * tuning.enemy_hp_multiplier * difficulty.hp();
+    let mut enemy = commands.spawn_bundle(SpriteBundle {
+        texture: game_textures.enemy.clone(),
+        transform: Transform {
+            scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
+            translation: Vec3::new(x, y, 10.),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    enemy
+        .insert(Enemy)
+        .insert(kind)
+        .insert(EnemyState::new(tuning, archetype.fire_rate))
+        .insert(Emitter::new(archetype.pattern))
+        .insert(formation)
+        .insert(SpriteSize::from(ENEMY_SIZE))
+        .insert(Health {hp, multiplier: 1.})
+        .insert(archetype.resistances)
+        .insert(StatusEffects::default())
+        .insert(Velocity{x:0.,y:0.});
+        // .insert(LastFired { time:-1., rate: 1.})
+        // .insert(NumberOfHits{hits:0});
+    if archetype.armour > 0. {
+        enemy.insert(Armour(archetype.armour));
+    }
+    if archetype.shield > 0. {
+        let shield = archetype.shield * tuning.enemy_hp_multiplier * difficulty.hp();
+        enemy.insert(Shield::new(shield, archetype.shield_regen));
+    }
+    if let Some(path) = archetype.script {
+        enemy
+            .insert(EnemyScript::new(path))
+            .insert(Movable { auto_despawn: true });
+    }
+    if archetype.behaviour.is_some() {
+        enemy.insert(EnemyBehaviour::new(hp));
+    }
+    if let Some(params) = &archetype.enrage {
+        enemy.insert(Rage::new(hp, params));
+    }
+    if let FirePattern::BulletMl { path } = archetype.pattern {
+        enemy.insert(BulletMlEmitter::new(path));
     }
+    enemy.id()
+}
+
+fn spawn_enemy_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    const USAGE: &str = "spawn enemy <archetype> <x> <y>";
+    let kind: EnemyKind = parse_arg(args, 0, USAGE)?;
+    let x: f32 = parse_arg(args, 1, USAGE)?;
+    let y: f32 = parse_arg(args, 2, USAGE)?;
+
+    let mut queue = CommandQueue::default();
+    let entity = {
+        let mut commands = Commands::new(&mut queue, world);
+        spawn_enemy(
+            &mut commands,
+            world.resource::<GameTextures>(),
+            world.resource::<Tuning>(),
+            world.resource::<Difficulty>(),
+            kind,
+            Formation::at(x, y),
+        )
+    };
+    queue.apply(world);
+    world.send_event(EnemySpawned {
+        entity,
+        position: Vec3::new(x, y, 10.),
+    });
+    Ok(format!("spawned {:?} at ({}, {})", kind, x, y))
 }
 
 // fn enemy_fire_criteria() -> ShouldRun {
@@ -67,49 +162,160 @@ fn enemy_spawn_system(
 //     }
 // }
 
+type EnemyFireQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static EnemyKind,
+        &'static mut EnemyState,
+        &'static mut Emitter,
+        Option<&'static mut BulletMlEmitter>,
+        Option<&'static Rage>,
+        Option<&'static EnemyBehaviour>,
+        Option<&'static StatusEffects>,
+    ),
+    BuiltInEnemy,
+>;
+
+#[allow(clippy::too_many_arguments)]
 fn enemy_fire_system(
     mut commands: Commands,
-    time: Res<Time>,
+    game_time: Res<GameTime>,
     game_textures: Res<GameTextures>,
-    // player_state: Res<PlayerState>,
-    mut enemy_query: Query<(Entity, &Transform, &mut Velocity, &mut EnemyState), With<Enemy>>,
+    player_state: Res<PlayerState>,
+    mut enemy_query: EnemyFireQuery,
     player_query: Query<&Transform, (Without<Enemy>, With<Player>)>,    
-    win_size: Res<WinSize>
+    win_size: Res<WinSize>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    mut bulletml_docs: BulletMlDocs,
+    mut rng: ResMut<GameRng>,
+    mut fired_events: EventWriter<ProjectileFired>,
 ) {
-    for (entity,&enemy_transform, mut velocity, mut enemy_state) in enemy_query.iter_mut() {
-        if enemy_state.fire_cooldown.tick(time.delta()).finished() {
-            enemy_state.fire_cooldown.reset();
-            let mut player_transform = &Transform::from_translation(Vec3::new(0.,0.,0.));
-            if player_query.get_single().is_ok() {
-                player_transform = player_query.get_single().unwrap();
+    // nobody to shoot at
+    let player_transform = match player_query.get_single() {
+        Ok(player_transform) => player_transform,
+        Err(_) => return,
+    };
+    for (entity,&enemy_transform, kind, mut enemy_state, mut emitter, bulletml_emitter, rage, behaviour, status) in enemy_query.iter_mut() {
+        if !behaviour.is_none_or(EnemyBehaviour::fires) {
+            continue;
+        }
+        // frozen guns: cooldowns and patterns pick up where they left off
+        if status.is_some_and(StatusEffects::is_frozen) {
+            continue;
+        }
+        let def = enemy_projectile(kind.archetype().projectile, &difficulty);
+        // enraged enemies, and harder games, run their fire patterns faster
+        let delta = game_time.delta().mul_f32(rage.map_or(1., |rage| rage.fire_rate) * difficulty.fire_rate());
+        let damage = enraged_damage(enemy_laser_damage(&difficulty, &tuning, kind.archetype().damage_kind), rage);
+        if let Some(mut bulletml_emitter) = bulletml_emitter {
+            // BulletML documents keep their own timing
+            if bulletml_emitter.runner.is_none() {
+                bulletml_emitter.runner = bulletml_docs.get(bulletml_emitter.path).map(Runner::top);
+            }
+            let runner = match &mut bulletml_emitter.runner {
+                Some(runner) => runner,
+                None => continue,
+            };
+            let mut ctx = Context {
+                position: enemy_transform.translation.truncate(),
+                target: Some(player_transform.translation.truncate()),
+                rank: tuning.bulletml_rank,
+                rand: &mut bulletml::rand(&mut rng),
             };
-            let (x, y) = (enemy_transform.translation.x, enemy_transform.translation.y);
-            let direction = (player_transform.translation.truncate() - enemy_transform.translation.truncate()).normalize();
-            commands
-                .spawn_bundle(SpriteBundle {
-                    texture: game_textures.enemy_laser.clone(),
-                    transform: Transform {
-                        translation: Vec3::new(x, y, 0.),
-                        rotation: enemy_transform.rotation,
-                        scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(Laser)
-                .insert(Damage{dmg: if time.seconds_since_startup() < 10. {1.} else {10.},multiplier:1.,limit:2.})
-                .insert(Movable {auto_despawn: true })
-                .insert(FromEnemy)
-                .insert(ParentEntity{entity})
-                .insert(SpriteSize::from(ENEMY_LASER_SIZE))
-                .insert(Velocity {x:direction.x,y:direction.y});
+            let shots = runner.update(delta.as_secs_f32(), &mut ctx);
+            let fired = bulletml::spawn_shots(
+                &mut commands,
+                &game_textures,
+                &def,
+                Shooter::Enemy(entity),
+                enemy_transform.translation,
+                shots,
+                damage,
+            );
+            for _ in fired {
+                fired_events.send(ProjectileFired {
+                    from_player: false,
+                    position: enemy_transform.translation,
+                });
+            }
+            continue;
+        }
+
+        let triggered = enemy_state.fire_cooldown.tick(delta).finished();
+        if triggered {
+            enemy_state.fire_cooldown.reset();
+        }
+        // lead the player by where it is heading, in pixels per second
+        let player_velocity = Vec2::new(player_state.delta_x, player_state.delta_y) / TIME_STEP;
+        let aim = lead_direction(
+            &kind.archetype().aim,
+            tuning.enemy_aim_skill,
+            enemy_transform.translation.truncate(),
+            player_transform.translation.truncate(),
+            player_velocity,
+            def.speed * BASE_SPEED,
+            &mut rng.0,
+        );
+        // sitting right on top of the player: shoot straight down
+        let aim = if aim == Vec2::ZERO { Vec2::NEG_Y } else { aim };
+        for direction in emitter.update(delta, triggered, aim) {
+            let spawned = projectile::fire(
+                &mut commands,
+                &game_textures,
+                &def,
+                Shooter::Enemy(entity),
+                enemy_transform.translation,
+                direction,
+                damage,
+            );
+            for _ in spawned {
+                fired_events.send(ProjectileFired {
+                    from_player: false,
+                    position: enemy_transform.translation,
+                });
+            }
         }
     }
 }
 
+/// Enemy shot damage of `kind`, climbing from `enemy_laser_damage` towards
+/// `enemy_laser_damage_late` along the difficulty curve.
+fn enemy_laser_damage(difficulty: &Difficulty, tuning: &Tuning, kind: DamageKind) -> Damage {
+    let dmg = tuning.enemy_laser_damage + (tuning.enemy_laser_damage_late - tuning.enemy_laser_damage) * difficulty.progress();
+    Damage{dmg,multiplier:1.,limit:2.,kind,crit_chance:0.}
+}
+
+/// `projectile` as enemies fire it at the current difficulty.
+fn enemy_projectile(projectile: ProjectileType, difficulty: &Difficulty) -> ProjectileDef {
+    let def = projectile.def();
+    ProjectileDef {
+        speed: def.speed * difficulty.projectile_speed(),
+        ..def
+    }
+}
+
+type EnemyMovementQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut Formation,
+        &'static mut Velocity,
+        &'static EnemyKind,
+        Option<&'static EnemyBehaviour>,
+        Option<&'static Rage>,
+    ),
+    BuiltInEnemy,
+>;
+
 fn enemy_movement_system(
     time: Res<Time>,
-    mut enemy_query: Query<(&mut Transform, &mut Formation, &mut Velocity), With<Enemy>>,
+    game_time: Res<GameTime>,
+    mut enemy_query: EnemyMovementQuery,
     mut player_query: Query<&Transform, (With<Player>,Without<Enemy>)>,
     win_size: Res<WinSize>,
 ) {
@@ -118,23 +324,45 @@ fn enemy_movement_system(
     let w_span = win_size.w / 2. - 100.;
     let h_span = win_size.h / 2. - 100.;
     // for each enemy
-    for (mut transform,mut formation, mut velocity) in enemy_query.iter_mut() {
-        
-        let mut player_transform = &Transform::from_translation(Vec3::new(0.,0.,0.));
-        if player_query.get_single().is_ok() {
-            player_transform = player_query.get_single().unwrap();
-        };
-
-        // let player_transform = player_query.get_single().unwrap_or_else(&Transform::default());
+    for (mut transform,mut formation, mut velocity, kind, behaviour, rage) in enemy_query.iter_mut() {
+        let player = player_query.get_single().ok().map(|tf| tf.translation);
         let (x_org, y_org) = (transform.translation.x, transform.translation.y);
-        let max_distance = TIME_STEP * formation.speed;
+        let step = TIME_STEP * game_time.time_scale() * rage.map_or(1., |rage| rage.speed);
+        let max_distance = step * formation.speed;
+
+        let movement = match (behaviour, kind.archetype().behaviour) {
+            (Some(behaviour), Some(params)) => behaviour.movement(&params),
+            _ => Movement::Orbit,
+        };
+        match movement {
+            Movement::Orbit => {}
+            // out of formation: head straight for the behaviour's target
+            Movement::Towards { target, speed } => {
+                let position = transform.translation.truncate();
+                let moved = (target - position).clamp_length_max(max_distance * speed);
+                transform.translation += moved.extend(0.);
+                if step > 0. {
+                    velocity.x = moved.x / step / BASE_SPEED;
+                    velocity.y = moved.y / step / BASE_SPEED;
+                }
+                if let Some(player) = player {
+                    face(&mut transform, player);
+                }
+                continue;
+            }
+            Movement::Spin(speed) => {
+                (velocity.x, velocity.y) = (0., 0.);
+                transform.rotate_z(speed * step);
+                continue;
+            }
+        }
         // let dir:i32 = rng.gen_range(-1..1); // -1 ccw, 1 cw
         let dir = if formation.start.0 < 0. {-1.} else { 1.};
         let (x_pivot,y_pivot) = formation.pivot;
         let (x_radius, y_radius) = formation.radius;
         
         // compute next angle
-        let angle = formation.angle + dir * formation.speed * TIME_STEP / (x_radius.min(y_radius)*PI/2.);
+        let angle = formation.angle + dir * formation.speed * step / (x_radius.min(y_radius)*PI/2.);
 
         // Compute target xy
         let x_dst = x_radius * angle.cos() + x_pivot;
@@ -159,9 +387,18 @@ fn enemy_movement_system(
         (translation.x,translation.y) = (x,y);
 
         // Rotate to face player
-        let diff = transform.translation - player_transform.translation;
+        if let Some(player) = player {
+            face(&mut transform, player);
+        }
+    }
+    
+}
+
+/// Turn an enemy's nose towards `target`; leaves it alone when they overlap.
+fn face(transform: &mut Transform, target: Vec3) {
+    let diff = transform.translation - target;
+    if diff.truncate() != Vec2::ZERO {
         let angle = diff.y.atan2(diff.x) - PI/2.; // Add/sub FRAC_PI here optionally
         transform.rotation = Quat::from_axis_angle(Vec3::new(0., 0., 1.), angle);
     }
-    
 }
@@ -0,0 +1,114 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+
+/// Bullet-hell emitter shapes, picked per archetype.
+#[derive(Clone, Copy, Debug)]
+pub enum FirePattern {
+    /// One shot straight at the player.
+    Aimed,
+    /// `count` shots fanned across `spread` radians around the player.
+    Burst { count: u32, spread: f32 },
+    /// `count` shots evenly around the enemy, starting at the player.
+    Ring { count: u32 },
+    /// `arms` evenly spaced shots whose angle keeps turning at
+    /// `angular_speed` radians per second.
+    Spiral { arms: u32, angular_speed: f32 },
+    /// `volleys` bursts in a row, `interval` seconds apart, each re-aimed.
+    Volley {
+        volleys: u32,
+        count: u32,
+        spread: f32,
+        interval: f32,
+    },
+    /// Driven by the BulletML document at this path under `assets/`, see
+    /// `BulletMlEmitter`.
+    BulletMl { path: &'static str },
+}
+
+/// Per-enemy emitter state: the spiral angle and any volleys still to come.
+#[derive(Component)]
+pub struct Emitter {
+    pattern: FirePattern,
+    angle: f32,
+    volleys_left: u32,
+    volley_timer: Timer,
+}
+
+impl Emitter {
+    pub fn new(pattern: FirePattern) -> Self {
+        let interval = match pattern {
+            FirePattern::Volley { interval, .. } => interval,
+            _ => 0.,
+        };
+        Self {
+            pattern,
+            angle: 0.,
+            volleys_left: 0,
+            // a zero-length repeating timer can't be ticked
+            volley_timer: Timer::from_seconds(interval.max(0.01), true),
+        }
+    }
+
+    /// Advance by `delta` and return the directions to fire this frame.
+    /// `triggered` starts a new salvo, `aim` points at the target.
+    pub fn update(&mut self, delta: Duration, triggered: bool, aim: Vec2) -> Vec<Vec2> {
+        let mut shots = Vec::new();
+        match self.pattern {
+            FirePattern::Aimed => {
+                if triggered {
+                    shots.push(aim);
+                }
+            }
+            FirePattern::Burst { count, spread } => {
+                if triggered {
+                    fan(&mut shots, aim, count, spread);
+                }
+            }
+            FirePattern::Ring { count } => {
+                if triggered {
+                    let count = count.max(1);
+                    for i in 0..count {
+                        shots.push(Vec2::from_angle(TAU * i as f32 / count as f32).rotate(aim));
+                    }
+                }
+            }
+            FirePattern::Spiral { arms, angular_speed } => {
+                self.angle = (self.angle + angular_speed * delta.as_secs_f32()) % TAU;
+                if triggered {
+                    let arms = arms.max(1);
+                    for i in 0..arms {
+                        let angle = self.angle + TAU * i as f32 / arms as f32;
+                        shots.push(Vec2::from_angle(angle));
+                    }
+                }
+            }
+            FirePattern::Volley { volleys, count, spread, .. } => {
+                if triggered {
+                    self.volleys_left = volleys;
+                    self.volley_timer.reset();
+                    // the first volley goes out right away
+                    self.volley_timer.set_elapsed(self.volley_timer.duration());
+                }
+                if self.volleys_left > 0 && self.volley_timer.tick(delta).just_finished() {
+                    self.volleys_left -= 1;
+                    fan(&mut shots, aim, count, spread);
+                }
+            }
+            FirePattern::BulletMl { .. } => {}
+        }
+        shots
+    }
+}
+
+fn fan(shots: &mut Vec<Vec2>, aim: Vec2, count: u32, spread: f32) {
+    let count = count.max(1);
+    for i in 0..count {
+        let offset = if count > 1 {
+            spread * (i as f32 / (count - 1) as f32 - 0.5)
+        } else {
+            0.
+        };
+        shots.push(Vec2::from_angle(offset).rotate(aim));
+    }
+}
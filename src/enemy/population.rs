@@ -0,0 +1,37 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::components::{Enemy, EnemyKind};
+
+/// Live enemy counts, rebuilt from the world every frame so that any way an
+/// enemy leaves play is accounted for.
+#[derive(Default)]
+pub struct EnemyPopulation {
+    counts: HashMap<EnemyKind, u32>,
+    total: u32,
+}
+
+impl EnemyPopulation {
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn count(&self, kind: EnemyKind) -> u32 {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&EnemyKind, &u32)> {
+        self.counts.iter()
+    }
+}
+
+pub fn enemy_population_system(
+    mut population: ResMut<EnemyPopulation>,
+    query: Query<&EnemyKind, With<Enemy>>,
+) {
+    population.counts.clear();
+    population.total = 0;
+    for kind in query.iter() {
+        *population.counts.entry(*kind).or_insert(0) += 1;
+        population.total += 1;
+    }
+}
@@ -0,0 +1,270 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use crate::{
+    components::{Enemy, EnemyKind, Player, Velocity},
+    difficulty::Difficulty,
+    events::ProjectileFired,
+    projectile::{self, Shooter},
+    status::StatusEffects,
+    tuning::Tuning,
+    EnemyState, GameTextures, GameTime,
+};
+
+use super::{
+    enemy_laser_damage, enemy_projectile,
+    enrage::{enraged_damage, Rage},
+};
+
+/// A compiled `.rhai` enemy script, reloaded whenever the file changes on disk.
+#[derive(TypeUuid)]
+#[uuid = "535ca355-b4c3-4cc6-a495-ce620ec18c7c"]
+pub struct EnemyScriptAsset(AST);
+
+#[derive(Default)]
+pub struct EnemyScriptLoader;
+
+impl AssetLoader for EnemyScriptLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let source = std::str::from_utf8(bytes)?;
+            // only parsing here, the hooks run on the engine in `EnemyScripts`
+            let ast = Engine::new_raw()
+                .compile(source)
+                .map_err(|err| bevy::asset::Error::msg(format!("{}: {}", load_context.path().display(), err)))?;
+            load_context.set_default_asset(LoadedAsset::new(EnemyScriptAsset(ast)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// The engine running enemy scripts, and handles of the scripts in use keyed
+/// by their path under `assets/`.
+pub struct EnemyScripts {
+    engine: Engine,
+    handles: HashMap<&'static str, Handle<EnemyScriptAsset>>,
+}
+
+impl Default for EnemyScripts {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine
+            .register_type_with_name::<ScriptEnemy>("Enemy")
+            .register_get("x", |enemy: &mut ScriptEnemy| enemy.x)
+            .register_get("y", |enemy: &mut ScriptEnemy| enemy.y)
+            .register_get("player_x", |enemy: &mut ScriptEnemy| enemy.player_x)
+            .register_get("player_y", |enemy: &mut ScriptEnemy| enemy.player_y)
+            .register_get("has_player", |enemy: &mut ScriptEnemy| enemy.has_player)
+            .register_get("aim", |enemy: &mut ScriptEnemy| {
+                (enemy.player_y - enemy.y).atan2(enemy.player_x - enemy.x)
+            })
+            .register_get("time", |enemy: &mut ScriptEnemy| enemy.time)
+            .register_get("dt", |enemy: &mut ScriptEnemy| enemy.dt)
+            .register_get_set(
+                "vx",
+                |enemy: &mut ScriptEnemy| enemy.vx,
+                |enemy: &mut ScriptEnemy, vx: f64| enemy.vx = vx,
+            )
+            .register_get_set(
+                "vy",
+                |enemy: &mut ScriptEnemy| enemy.vy,
+                |enemy: &mut ScriptEnemy, vy: f64| enemy.vy = vy,
+            )
+            .register_get_set(
+                "memory",
+                |enemy: &mut ScriptEnemy| enemy.memory.clone(),
+                |enemy: &mut ScriptEnemy, memory: Map| enemy.memory = memory,
+            )
+            .register_fn("fire", |enemy: &mut ScriptEnemy, dx: f64, dy: f64| {
+                enemy.shots.push(Vec2::new(dx as f32, dy as f32));
+            });
+        Self {
+            engine,
+            handles: HashMap::default(),
+        }
+    }
+}
+
+impl EnemyScripts {
+    /// Start loading `path` through the asset server, if nothing did yet.
+    fn load(&mut self, path: &'static str, asset_server: &AssetServer) {
+        self.handles.entry(path).or_insert_with(|| asset_server.load(path));
+    }
+
+    /// The script at `path`, `None` until it has loaded (or if it failed to).
+    fn get<'a>(&self, path: &str, assets: &'a Assets<EnemyScriptAsset>) -> Option<&'a AST> {
+        let handle = self.handles.get(path)?;
+        assets.get(handle).map(|script| &script.0)
+    }
+
+    /// Run `hook` if the script defines it, errors are logged and otherwise ignored.
+    fn call(&self, path: &str, ast: &AST, hook: &str, this: &mut Dynamic) {
+        if !ast.iter_functions().any(|f| f.name == hook) {
+            return;
+        }
+        let options = CallFnOptions::new().bind_this_ptr(this);
+        if let Err(err) = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, hook, ())
+        {
+            error!("enemy script {} failed in {}: {}", path, hook, err);
+        }
+    }
+}
+
+/// Drives an enemy from a script instead of the built-in movement and firing.
+#[derive(Component)]
+pub struct EnemyScript {
+    path: &'static str,
+    memory: Map,
+    spawned: bool,
+}
+
+impl EnemyScript {
+    pub fn new(path: &'static str) -> Self {
+        Self {
+            path,
+            memory: Map::new(),
+            spawned: false,
+        }
+    }
+}
+
+/// The `this` of script hooks: a snapshot of the enemy and its surroundings,
+/// read back after the hook to apply velocity and spawn shots.
+#[derive(Clone)]
+struct ScriptEnemy {
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    player_x: f64,
+    player_y: f64,
+    has_player: bool,
+    time: f64,
+    dt: f64,
+    memory: Map,
+    shots: Vec<Vec2>,
+}
+
+type ScriptedEnemyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static EnemyKind,
+        &'static mut Transform,
+        &'static mut Velocity,
+        &'static mut EnemyState,
+        &'static mut EnemyScript,
+        Option<&'static Rage>,
+        Option<&'static StatusEffects>,
+    ),
+    With<Enemy>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn scripted_enemy_system(
+    mut commands: Commands,
+    mut scripts: ResMut<EnemyScripts>,
+    script_assets: Res<Assets<EnemyScriptAsset>>,
+    asset_server: Res<AssetServer>,
+    game_time: Res<GameTime>,
+    game_textures: Res<GameTextures>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    mut query: ScriptedEnemyQuery,
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut fired_events: EventWriter<ProjectileFired>,
+) {
+    let player = player_query.get_single().ok().map(|tf| tf.translation.truncate());
+    for (_, _, _, _, _, script, _, _) in query.iter() {
+        scripts.load(script.path, &asset_server);
+    }
+    let scripts = &*scripts;
+
+    for (entity, kind, mut transform, mut velocity, mut enemy_state, mut script, rage, status) in query.iter_mut() {
+        let ast = match scripts.get(script.path, &script_assets) {
+            Some(ast) => ast,
+            None => continue,
+        };
+        let position = transform.translation.truncate();
+        let player_position = player.unwrap_or(position);
+        // scripts steer at normal speed, rage speeds them up and slows hold them back
+        let speed = rage.map_or(1., |rage| rage.speed) * status.map_or(1., StatusEffects::speed);
+        let mut this = Dynamic::from(ScriptEnemy {
+            x: position.x as f64,
+            y: position.y as f64,
+            vx: (velocity.x / speed) as f64,
+            vy: (velocity.y / speed) as f64,
+            player_x: player_position.x as f64,
+            player_y: player_position.y as f64,
+            has_player: player.is_some(),
+            time: game_time.seconds_since_startup(),
+            dt: game_time.delta_seconds() as f64,
+            memory: std::mem::take(&mut script.memory),
+            shots: Vec::new(),
+        });
+
+        if !script.spawned {
+            script.spawned = true;
+            scripts.call(script.path, ast, "on_spawn", &mut this);
+        }
+        if !game_time.is_stopped() {
+            scripts.call(script.path, ast, "on_tick", &mut this);
+        }
+        let fire_rate = rage.map_or(1., |rage| rage.fire_rate) * difficulty.fire_rate();
+        let frozen = status.is_some_and(StatusEffects::is_frozen);
+        if !frozen && enemy_state.fire_cooldown.tick(game_time.delta().mul_f32(fire_rate)).finished() {
+            enemy_state.fire_cooldown.reset();
+            // nothing to shoot at
+            if player.is_some() {
+                scripts.call(script.path, ast, "on_fire", &mut this);
+            }
+        }
+
+        let result = this.cast::<ScriptEnemy>();
+        velocity.x = result.vx as f32 * speed;
+        velocity.y = result.vy as f32 * speed;
+        script.memory = result.memory;
+
+        // Rotate to face player
+        let diff = position - player_position;
+        if diff != Vec2::ZERO {
+            transform.rotation = Quat::from_rotation_z(diff.y.atan2(diff.x) - PI / 2.);
+        }
+        let def = enemy_projectile(kind.archetype().projectile, &difficulty);
+        for direction in result.shots {
+            let spawned = projectile::fire(
+                &mut commands,
+                &game_textures,
+                &def,
+                Shooter::Enemy(entity),
+                transform.translation,
+                direction,
+                enraged_damage(enemy_laser_damage(&difficulty, &tuning, kind.archetype().damage_kind), rage),
+            );
+            for _ in spawned {
+                fired_events.send(ProjectileFired {
+                    from_player: false,
+                    position: transform.translation,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{
+    console::parse_arg,
+    difficulty::Difficulty,
+    events::{BossDefeated, EnemyKilled, WaveCleared},
+    tuning::Tuning,
+    GameTextures, WinSize,
+};
+
+use super::{
+    boss::{self, BossKind},
+    population::EnemyPopulation,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveStage {
+    /// Regular enemies spawn until the wave's kills are in.
+    Fighting,
+    /// No more spawns, waiting for the field to empty before the boss.
+    Clearing,
+    /// The boss is on the field.
+    Boss,
+}
+
+/// Where the current wave is at: kills count towards the boss, beating the
+/// boss clears the wave and starts the next one.
+pub struct Waves {
+    /// 1 for the first wave.
+    pub number: u32,
+    pub stage: WaveStage,
+    kills: u32,
+}
+
+impl Default for Waves {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            stage: WaveStage::Fighting,
+            kills: 0,
+        }
+    }
+}
+
+impl Waves {
+    /// Whether regular enemies should keep coming.
+    pub fn spawning(&self) -> bool {
+        self.stage == WaveStage::Fighting
+    }
+
+    /// Skip straight to the boss.
+    pub fn call_boss(&mut self) {
+        if self.stage == WaveStage::Fighting {
+            self.stage = WaveStage::Clearing;
+        }
+    }
+
+    /// Start wave `number` from its first kill.
+    pub fn skip_to(&mut self, number: u32) {
+        self.number = number.max(1);
+        self.stage = WaveStage::Fighting;
+        self.kills = 0;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn wave_system(
+    mut commands: Commands,
+    mut waves: ResMut<Waves>,
+    tuning: Res<Tuning>,
+    difficulty: Res<Difficulty>,
+    population: Res<EnemyPopulation>,
+    game_textures: Res<GameTextures>,
+    win_size: Res<WinSize>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut defeated_events: EventReader<BossDefeated>,
+    mut cleared_events: EventWriter<WaveCleared>,
+) {
+    let kills = killed_events.iter().count() as u32;
+    match waves.stage {
+        WaveStage::Fighting => {
+            waves.kills += kills;
+            if waves.kills as f32 >= tuning.wave_kills {
+                waves.stage = WaveStage::Clearing;
+            }
+        }
+        WaveStage::Clearing => {
+            if population.total() == 0 {
+                let kind = BossKind::for_wave(waves.number);
+                boss::spawn_boss(&mut commands, &game_textures, &tuning, &difficulty, &win_size, kind);
+                info!("wave {}: {:?} incoming", waves.number, kind);
+                waves.stage = WaveStage::Boss;
+            }
+        }
+        WaveStage::Boss => {
+            if defeated_events.iter().next().is_some() {
+                cleared_events.send(WaveCleared { wave: waves.number });
+                waves.number += 1;
+                waves.kills = 0;
+                waves.stage = WaveStage::Fighting;
+            }
+        }
+    }
+}
+
+pub fn wave_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let mut waves = world.resource_mut::<Waves>();
+    match args.first() {
+        None => {}
+        Some(&"boss") => waves.call_boss(),
+        Some(_) => waves.skip_to(parse_arg(args, 0, "wave [<number>|boss]")?),
+    }
+    Ok(format!("wave {}: {:?}, {} kills", waves.number, waves.stage, waves.kills))
+}
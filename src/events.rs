@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{DamageKind, ExplosionToSpawn},
+    PlayerState,
+};
+
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<EnemyKilled>()
+            .add_event::<EnemySpawned>()
+            .add_event::<EnemyHit>()
+            .add_event::<PlayerDamaged>()
+            .add_event::<PlayerDied>()
+            .add_event::<ProjectileFired>()
+            .add_event::<BossDefeated>()
+            .add_event::<WaveCleared>()
+            .add_system(score_listener_system)
+            .add_system(explosion_listener_system);
+    }
+}
+
+// region: --- Events
+/// A hit on `target`, resolved against its `Health` by the damage pipeline.
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: DamageSource,
+    /// Before crits, armour and the target's multiplier.
+    pub amount: f32,
+    pub kind: DamageKind,
+    pub crit_chance: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DamageSource {
+    Player,
+    /// The enemy that fired, which may be gone by now.
+    Enemy(Entity),
+    /// A burn ticking away, which ignores armour and immunity.
+    Burning,
+}
+
+pub struct EnemyKilled {
+    pub entity: Entity,
+    pub position: Vec3,
+}
+
+pub struct EnemyHit {
+    pub entity: Entity,
+    pub source: DamageSource,
+    pub amount: f32,
+}
+
+pub struct EnemySpawned {
+    pub entity: Entity,
+    pub position: Vec3,
+}
+
+pub struct PlayerDamaged {
+    pub amount: f32,
+    pub position: Vec3,
+}
+
+pub struct PlayerDied {
+    pub position: Vec3,
+}
+
+pub struct ProjectileFired {
+    pub from_player: bool,
+    pub position: Vec3,
+}
+
+/// Sent once a boss has finished blowing up.
+pub struct BossDefeated {
+    pub entity: Entity,
+    pub position: Vec3,
+}
+
+pub struct WaveCleared {
+    pub wave: u32,
+}
+// endregion: --- Events
+
+// Score for finishing off a boss, on top of its parts
+const BOSS_SCORE: f64 = 10.;
+
+fn score_listener_system(
+    mut player_state: ResMut<PlayerState>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut boss_events: EventReader<BossDefeated>,
+) {
+    for _ in killed_events.iter() {
+        player_state.score += 1.;
+    }
+    for _ in boss_events.iter() {
+        player_state.score += BOSS_SCORE;
+    }
+}
+
+fn explosion_listener_system(
+    mut commands: Commands,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut died_events: EventReader<PlayerDied>,
+) {
+    for event in killed_events.iter() {
+        commands.spawn().insert(ExplosionToSpawn(event.position));
+    }
+    for event in died_events.iter() {
+        commands.spawn().insert(ExplosionToSpawn(event.position));
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::console::{parse_arg, ConsoleAppExt};
+
+// Slowest game speed reachable through the assist toggle
+const ASSIST_TIME_SCALE: f32 = 0.6;
+
+pub struct GameTimePlugin;
+
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameTime::default())
+            .add_system_to_stage(CoreStage::PreUpdate, game_time_system)
+            .add_system(game_time_keyboard_system)
+            .register_console_command("timescale", "timescale <scale>", timescale_command)
+            .register_console_command("pause", "pause", pause_command);
+    }
+}
+
+/// Scaled clock that all gameplay timers and movement should tick on.
+///
+/// `scale` is the base game speed (1. normal, lower for assist mode). Pause,
+/// hit-stop and slow-motion are layered on top of it and count down in real time.
+/// With `fixed_step` set, every frame advances by that much instead of the
+/// measured frame time, which makes runs reproducible.
+pub struct GameTime {
+    pub scale: f32,
+    pub paused: bool,
+    pub fixed_step: Option<Duration>,
+    slow_motion: Option<(f32, Timer)>,
+    hit_stop: Option<Timer>,
+    delta: Duration,
+    elapsed: f64,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            paused: false,
+            fixed_step: None,
+            slow_motion: None,
+            hit_stop: None,
+            delta: Duration::ZERO,
+            elapsed: 0.,
+        }
+    }
+}
+
+impl GameTime {
+    /// Effective scale applied this frame, 0. while paused or in hit-stop.
+    pub fn time_scale(&self) -> f32 {
+        if self.paused || self.hit_stop.is_some() {
+            return 0.;
+        }
+        match &self.slow_motion {
+            Some((factor, _)) => self.scale * factor,
+            None => self.scale,
+        }
+    }
+
+    /// Scaled frame delta, use in place of `Time::delta`.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Scaled time since startup, use in place of `Time::seconds_since_startup`.
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.time_scale() == 0.
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Bullet-time: run at `factor` of the base speed for `secs` real seconds.
+    pub fn slow_motion(&mut self, factor: f32, secs: f32) {
+        self.slow_motion = Some((factor, Timer::from_seconds(secs, false)));
+    }
+
+    /// Freeze the simulation for `secs` real seconds, e.g. on a kill.
+    pub fn hit_stop(&mut self, secs: f32) {
+        self.hit_stop = Some(Timer::from_seconds(secs, false));
+    }
+
+    /// Back to the start of a run: the clock at zero and no pause, hit-stop
+    /// or slow-motion left over. `scale` and `fixed_step` are kept.
+    pub fn restart(&mut self) {
+        self.paused = false;
+        self.slow_motion = None;
+        self.hit_stop = None;
+        self.delta = Duration::ZERO;
+        self.elapsed = 0.;
+    }
+
+    fn tick(&mut self, real_delta: Duration) {
+        let real_delta = self.fixed_step.unwrap_or(real_delta);
+        if let Some(timer) = &mut self.hit_stop {
+            if timer.tick(real_delta).finished() {
+                self.hit_stop = None;
+            }
+        }
+        if let Some((_, timer)) = &mut self.slow_motion {
+            if timer.tick(real_delta).finished() {
+                self.slow_motion = None;
+            }
+        }
+        self.delta = real_delta.mul_f32(self.time_scale());
+        self.elapsed += self.delta.as_secs_f64();
+    }
+}
+
+fn game_time_system(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+    game_time.tick(time.delta());
+}
+
+fn game_time_keyboard_system(kb: Res<Input<KeyCode>>, mut game_time: ResMut<GameTime>) {
+    if kb.just_pressed(KeyCode::P) {
+        game_time.toggle_pause();
+    }
+    if kb.just_pressed(KeyCode::F2) {
+        game_time.scale = if game_time.scale < 1. { 1. } else { ASSIST_TIME_SCALE };
+    }
+}
+
+fn timescale_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let scale: f32 = parse_arg(args, 0, "timescale <scale>")?;
+    if scale < 0. {
+        return Err("time scale must not be negative".to_string());
+    }
+    world.resource_mut::<GameTime>().scale = scale;
+    Ok(format!("time scale set to {}", scale))
+}
+
+fn pause_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut game_time = world.resource_mut::<GameTime>();
+    game_time.toggle_pause();
+    Ok(if game_time.paused { "paused" } else { "resumed" }.to_string())
+}
@@ -0,0 +1,79 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerActions::default())
+            .insert_resource(InputSource::Keyboard)
+            .add_system(keyboard_actions_system.label(PlayerInputLabel));
+    }
+}
+
+/// Systems producing `PlayerActions` run in this label, consumers run after it.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputLabel;
+
+/// Who drives `PlayerActions` this frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputSource {
+    Keyboard,
+    Autopilot,
+    /// External agent over the step protocol, see `agent`.
+    Agent,
+}
+
+/// What the player wants to do this frame, independent of where it came from.
+#[derive(Default, Debug)]
+pub struct PlayerActions {
+    /// Thrust per axis, each in -1..=1.
+    pub movement: Vec2,
+    /// Fire angle in radians, 0 is up and PI/2 right; `None` holds fire.
+    pub aim: Option<f32>,
+    /// Raise the shield, if it's ready.
+    pub shield: bool,
+}
+
+fn keyboard_actions_system(
+    kb: Res<Input<KeyCode>>,
+    source: Res<InputSource>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    if *source != InputSource::Keyboard {
+        return;
+    }
+    let mut movement = Vec2::ZERO;
+    if kb.pressed(KeyCode::A) {
+        movement.x -= 1.;
+    }
+    if kb.pressed(KeyCode::D) {
+        movement.x += 1.;
+    }
+    if kb.pressed(KeyCode::S) {
+        movement.y -= 1.;
+    }
+    if kb.pressed(KeyCode::W) {
+        movement.y += 1.;
+    }
+
+    // later keys win, as before
+    let mut aim = None;
+    if kb.pressed(KeyCode::Up) {
+        aim = Some(0.);
+    }
+    if kb.pressed(KeyCode::Down) {
+        aim = Some(PI);
+    }
+    if kb.pressed(KeyCode::Left) {
+        aim = Some(-PI / 2.);
+    }
+    if kb.pressed(KeyCode::Right) {
+        aim = Some(PI / 2.);
+    }
+
+    actions.movement = movement;
+    actions.aim = aim;
+    actions.shield = kb.just_pressed(KeyCode::Space);
+}
@@ -3,20 +3,62 @@
 use std::time::Duration;
 
 use bevy::{
-    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
-    math::Vec3Swizzles, prelude::*, render::camera::ScalingMode, sprite::collide_aabb::collide,
-    text, utils::HashSet, time::Stopwatch,
+    app::ScheduleRunnerPlugin,
+    asset::AssetServerSettings,
+    ecs::schedule::SingleThreadedExecutor,
+    log::LogPlugin,
+    math::Vec3Swizzles,
+    render::settings::WgpuSettings,
+    winit::WinitPlugin, prelude::*, render::camera::{ScalingMode, Viewport}, sprite::collide_aabb::collide,
+    text, time::Stopwatch, window::{WindowResized, WindowSettings},
 };
 use components::{
     Enemy, Explosion, ExplosionTimer, ExplosionToSpawn, FromPlayer, Laser, Movable, SpriteSize,
-    Velocity, FromEnemy, Player, Health, ScoreText, Damage, ParentEntity
+    Velocity, FromEnemy, Player, Health, ScoreText, Damage, ParentEntity, Despawn, Piercing, Immunity
 };
+use agent::{AgentPlugin, AgentTransport};
+use arena::ArenaPlugin;
+use autopilot::AutopilotPlugin;
+use bulletml::BulletMlPlugin;
+use console::ConsolePlugin;
+use damage::{DamageLabel, DamagePlugin};
+use debug::DebugPlugin;
+use difficulty::DifficultyPlugin;
 use enemy::EnemyPlugin;
+use events::{DamageEvent, DamageSource, GameEventsPlugin};
+use game_time::{GameTime, GameTimePlugin};
+use input::InputPlugin;
 use player::PlayerPlugin;
-
+use projectile::ProjectilePlugin;
+use rand::{rngs::StdRng, SeedableRng};
+use shield::ShieldPlugin;
+use sound::SoundPlugin;
+use stats::StatsPlugin;
+use status::StatusPlugin;
+use tuning::Tuning;
+
+mod agent;
+mod arena;
+mod autopilot;
+mod bulletml;
+mod cli;
 mod components;
+mod console;
+mod damage;
+mod debug;
+mod difficulty;
 mod enemy;
+mod events;
+mod game_time;
+mod input;
 mod player;
+mod projectile;
+mod shield;
+mod sim;
+mod sound;
+mod stats;
+mod status;
+mod tuning;
 
 // region: --- Asset Constants
 const PLAYER_SIZE: (f32, f32) = (144., 75.); //(14., 7.5)
@@ -35,19 +77,25 @@ const SPRITE_SCALE: f32 = 0.5;
 const EXPLOSION_SHEET: &str = "explo_a_sheet.png";
 const EXPLOSION_LEN: usize = 16;
 
-const ENEMY_MAX: u32 = 3;
 const FORMATION_MEMBERS_MAX: u32 = 1;
 // endregion: --- Asset Constants
 
 // region:    --- Game Constants
+// Logical play-field size, the camera letterboxes it into any window
+const PLAY_FIELD: (f32, f32) = (2560. / 2., 1440. / 2.);
 const TIME_STEP: f32 = 1. / 60.;
 const BASE_SPEED: f32 = 250.;
+const KILL_HIT_STOP: f32 = 0.05;
 // endregion: --- Game Constants
 
 // region: --- Resources
+/// `w`/`h` are the logical play-field size gameplay works in,
+/// `window_w`/`window_h` the current window size in logical pixels.
 pub struct WinSize {
     pub w: f32,
     pub h: f32,
+    pub window_w: f32,
+    pub window_h: f32,
 }
 struct GameTextures {
     player: Handle<Image>,
@@ -57,47 +105,42 @@ struct GameTextures {
     explosion: Handle<TextureAtlas>,
 }
 
-struct EnemyCount(u32);
-
 struct PlayerState {
     on: bool,
-    health: Health,
     fire_cooldown: Timer,
-    immunity_cooldown: Timer,
     spawn_cooldown: Timer,
     angle: f32,
     score: f64,
     velocity: f32,
     firing: bool,
     delta_x: f32,
-    delta_y: f32
+    delta_y: f32,
+    god: bool,
 }
 
 impl PlayerState {
     pub fn spawned(&mut self) {
         self.spawn_cooldown.reset();
         self.on = true;
-        self.immunity_cooldown.reset();
         self.angle = 0.;
         self.velocity = 1.;
         self.score = 0.;
     }
 }
 
-impl Default for PlayerState {
-    fn default() -> Self {
+impl PlayerState {
+    pub fn new(tuning: &Tuning) -> Self {
         Self { 
             on: false,
-            health: Health{hp: 3., multiplier: 1.},
-            fire_cooldown: Timer::new(Duration::from_secs_f32(0.5), false),
-            immunity_cooldown: Timer::new(Duration::from_secs_f32(4.), false),
-            spawn_cooldown: Timer::new(Duration::from_secs_f32(2.), false),
+            fire_cooldown: Timer::new(Duration::from_secs_f32(tuning.player_fire_cooldown), false),
+            spawn_cooldown: Timer::new(Duration::from_secs_f32(tuning.player_respawn), false),
             score: 0.,
             angle: 0.,
             velocity: 1.,
             firing: false,
             delta_x: 0.,
             delta_y: 0.,
+            god: false,
         }
     }
 }
@@ -108,37 +151,132 @@ struct EnemyState {
     // angle: f32,
 }
 
-impl Default for EnemyState {
-    fn default() -> Self {
+impl EnemyState {
+    /// `fire_rate` salvos per `enemy_fire_cooldown`.
+    pub fn new(tuning: &Tuning, fire_rate: f32) -> Self {
         Self {
-            fire_cooldown: Timer::new(Duration::from_secs_f32(1.), false),
+            fire_cooldown: Timer::new(Duration::from_secs_f32(tuning.enemy_fire_cooldown / fire_rate), false),
         }
     }
 }
 
+/// Source of all gameplay randomness, seedable for reproducible runs.
+pub struct GameRng(pub StdRng);
+
 // endregion: --- Resources
 
+/// How `build_app` puts the game together.
+#[derive(Default)]
+pub struct AppOptions {
+    /// No window or renderer, for agents and batch simulation.
+    pub headless: bool,
+    /// Leave stdout and stderr to the caller.
+    pub quiet: bool,
+    pub seed: Option<u64>,
+    pub tuning: Tuning,
+}
+
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
+    let args = cli::Args::parse();
+    if args.simulate {
+        sim::run(&args);
+        return;
+    }
+    let mut app = build_app(&AppOptions {
+        headless: args.headless,
+        // stdout carries the protocol, keep logging off it
+        quiet: matches!(args.agent, Some(AgentTransport::Stdio)),
+        seed: args.seed,
+        tuning: args.tuning.clone(),
+    });
+    if let Some(transport) = args.agent {
+        app.add_plugin(AgentPlugin { transport });
+    }
+    app.run();
+}
+
+fn build_app(options: &AppOptions) -> App {
+    let mut app = App::new();
+    let rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    app.insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
         .insert_resource(WindowDescriptor {
             title: "Ant Invaders".to_string(),
-            width: 2560./2.,
-            height: 1440./2.,
+            width: PLAY_FIELD.0,
+            height: PLAY_FIELD.1,
             position: WindowPosition::Centered(MonitorSelection::Primary),
             ..Default::default()
         })
-        .add_plugins(DefaultPlugins)
+        .insert_resource(options.tuning.clone())
+        .insert_resource(GameRng(rng));
+    if options.headless {
+        app.insert_resource(WgpuSettings {
+            backends: None,
+            ..default()
+        })
+        // no window ever opens, that's no reason to quit
+        .insert_resource(WindowSettings {
+            exit_on_all_closed: false,
+            ..default()
+        });
+    } else {
+        // pick up edited enemy scripts and bullet patterns without a restart
+        app.insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..default()
+        });
+    }
+    let (headless, quiet) = (options.headless, options.quiet);
+    app.add_plugins_with(DefaultPlugins, |group| {
+        if headless {
+            group.disable::<WinitPlugin>();
+        }
+        if quiet {
+            group.disable::<LogPlugin>();
+        }
+        group
+    });
+    if headless {
+        app.add_plugin(ScheduleRunnerPlugin);
+    }
+    app.add_plugin(ConsolePlugin)
+        .add_plugin(GameTimePlugin)
+        .add_plugin(GameEventsPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(StatusPlugin)
+        .add_plugin(ShieldPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(DifficultyPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(ArenaPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(AutopilotPlugin)
+        .add_plugin(ProjectilePlugin)
+        .add_plugin(BulletMlPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(EnemyPlugin)
+        .add_plugin(DebugPlugin)
         .add_startup_system(setup_system)
         .add_system(movable_system)
-        .add_system(player_laser_hit_enemy_system)
+        .add_system(player_laser_hit_enemy_system.before(DamageLabel))
         .add_system(explosion_to_spawn_system)
         .add_system(explosion_animation_system)
-        .add_system(enemy_laser_hit_player_system)
+        .add_system(enemy_laser_hit_player_system.before(DamageLabel))
         .add_system(text_score_system)
-        .run();
+        .add_system(window_resize_system)
+        .add_system_to_stage(CoreStage::Last, despawn_system);
+    if options.seed.is_some() {
+        // run systems one at a time so seeded games don't race between threads
+        for stage in [CoreStage::First, CoreStage::PreUpdate, CoreStage::Update, CoreStage::PostUpdate, CoreStage::Last] {
+            app.stage(stage, |stage: &mut SystemStage| {
+                stage.set_executor(Box::new(SingleThreadedExecutor));
+                stage
+            });
+        }
+    }
+    app
 }
 
 fn setup_system(
@@ -148,8 +286,20 @@ fn setup_system(
     mut windows: ResMut<Windows>,
     player_state: Res<PlayerState>,
 ) {
+    // capture window size, headless runs have none
+    let window = windows.get_primary_mut();
+    let (win_w, win_h) = window
+        .as_ref()
+        .map_or(PLAY_FIELD, |window| (window.width(), window.height()));
+
     // camera
-    commands.spawn_bundle(Camera2dBundle::default()).insert(UiCameraConfig {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::Auto {
+        min_width: PLAY_FIELD.0,
+        min_height: PLAY_FIELD.1,
+    };
+    camera.camera.viewport = window.map(|window| letterbox_viewport(window.physical_width(), window.physical_height()));
+    commands.spawn_bundle(camera).insert(UiCameraConfig {
         show_ui: true,
         ..default()
     });
@@ -185,15 +335,16 @@ fn setup_system(
         })
         .insert(ScoreText);
 
-    // capture window size
-    let window = windows.get_primary_mut().unwrap();
-    let (win_w, win_h) = (window.width(), window.height());
-
     // position window
     // window.set_position(IVec2::new(2780, 4900));
 
     // add winsize
-    let win_size = WinSize { w: win_w, h: win_h };
+    let win_size = WinSize {
+        w: PLAY_FIELD.0,
+        h: PLAY_FIELD.1,
+        window_w: win_w,
+        window_h: win_h,
+    };
     commands.insert_resource(win_size);
 
     // load explosion
@@ -210,18 +361,19 @@ fn setup_system(
         explosion,
     };
     commands.insert_resource(game_textures);
-    commands.insert_resource(EnemyCount(0));
 }
 
 fn movable_system(
     mut commands: Commands,
     win_size: Res<WinSize>,
+    game_time: Res<GameTime>,
     mut query: Query<(Entity, &Velocity, &mut Transform, &Movable)>,
 ) {
+    let step = TIME_STEP * game_time.time_scale();
     for (entity, velocity, mut transform, movable) in query.iter_mut() {
         let translation = &mut transform.translation;
-        translation.x += velocity.x * BASE_SPEED * TIME_STEP;
-        translation.y += velocity.y * BASE_SPEED * TIME_STEP;
+        translation.x += velocity.x * BASE_SPEED * step;
+        translation.y += velocity.y * BASE_SPEED * step;
 
         if movable.auto_despawn {
             const MARGIN: f32 = 200.;
@@ -230,29 +382,84 @@ fn movable_system(
                 || translation.x > win_size.w / 2. + MARGIN
                 || translation.x < -win_size.w / 2. - MARGIN
             {
-                commands.entity(entity).despawn();
+                commands.entity(entity).insert(Despawn);
             }
         }
     }
 }
 
-fn player_laser_hit_enemy_system(
+/// Largest viewport with the play-field aspect ratio, centred in the window.
+fn letterbox_viewport(physical_w: u32, physical_h: u32) -> Viewport {
+    let scale = (physical_w as f32 / PLAY_FIELD.0).min(physical_h as f32 / PLAY_FIELD.1);
+    let size = UVec2::new(
+        ((PLAY_FIELD.0 * scale) as u32).max(1),
+        ((PLAY_FIELD.1 * scale) as u32).max(1),
+    );
+    Viewport {
+        physical_position: UVec2::new(
+            physical_w.saturating_sub(size.x) / 2,
+            physical_h.saturating_sub(size.y) / 2,
+        ),
+        physical_size: size,
+        ..default()
+    }
+}
+
+fn window_resize_system(
+    mut resize_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut win_size: ResMut<WinSize>,
+    mut camera_query: Query<&mut Camera, With<Camera2d>>,
+) {
+    for event in resize_events.iter() {
+        let window = match windows.get(event.id) {
+            Some(window) if window.id().is_primary() => window,
+            _ => continue,
+        };
+        win_size.window_w = event.width;
+        win_size.window_h = event.height;
+        for mut camera in camera_query.iter_mut() {
+            camera.viewport = Some(letterbox_viewport(window.physical_width(), window.physical_height()));
+        }
+    }
+}
+
+/// Single place where entities leave the world. Systems mark entities with
+/// `Despawn` and they are removed, along with their children, at the end of the frame.
+fn despawn_system(
     mut commands: Commands,
-    mut enemy_count: ResMut<EnemyCount>,
-    mut player_state: ResMut<PlayerState>,
-    laser_query: Query<(Entity, &Transform, &SpriteSize, &Damage), (With<FromPlayer>, With<Laser>)>,
-    mut enemy_query: Query<(Entity, &Transform, &SpriteSize, &mut Health), With<Enemy>>,
+    query: Query<(Entity, Option<&Parent>), With<Despawn>>,
+    marked_query: Query<(), With<Despawn>>,
 ) {
-    let mut despwaned_entities: HashSet<Entity> = HashSet::new();
-    for (laser_entity, laser_tf, laser_size, laser_damage) in laser_query.iter() {
-        if despwaned_entities.contains(&laser_entity) {
-            continue;
+    for (entity, parent) in query.iter() {
+        // the parent's recursive despawn already takes care of this one
+        if let Some(parent) = parent {
+            if marked_query.contains(parent.get()) {
+                continue;
+            }
         }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type PlayerLaserQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Transform, &'static SpriteSize, &'static Damage, Option<&'static mut Piercing>),
+    (With<FromPlayer>, With<Laser>),
+>;
+
+fn player_laser_hit_enemy_system(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut laser_query: PlayerLaserQuery,
+    enemy_query: Query<(Entity, &Transform, &SpriteSize), With<Enemy>>,
+) {
+    for (laser_entity, laser_tf, laser_size, laser_damage, mut piercing) in laser_query.iter_mut() {
         let laser_scale = Vec2::from(laser_tf.scale.xy());
-        for (enemy_entity, enemy_tf, enemy_size, mut enemy_health) in enemy_query.iter_mut() {
-            if despwaned_entities.contains(&laser_entity)
-                || despwaned_entities.contains(&enemy_entity)
-            {
+        for (enemy_entity, enemy_tf, enemy_size) in enemy_query.iter() {
+            // a piercing laser hits each enemy only once on its way through
+            if piercing.as_ref().is_some_and(|p| p.hit.contains(&enemy_entity)) {
                 continue;
             }
             let enemy_scale = Vec2::from(enemy_tf.scale.xy());
@@ -263,23 +470,16 @@ fn player_laser_hit_enemy_system(
                 enemy_size.0 * enemy_scale,
             );
             if let Some(_) = collision {
-                enemy_health.hp -= laser_damage.dmg;
-                if enemy_health.hp <= 0. {
-                    commands.entity(enemy_entity).despawn();
-                    despwaned_entities.insert(enemy_entity);
-                    enemy_count.0 -= 1;
-
-                    commands.entity(laser_entity).despawn();
-                    despwaned_entities.insert(laser_entity);
-
-                    commands
-                        .spawn()
-                        .insert(ExplosionToSpawn(enemy_tf.translation.clone()));
-                    player_state.score += 1.;
-
-                } else {
-                    commands.entity(laser_entity).despawn();
-                    despwaned_entities.insert(laser_entity);
+                damage_events.send(laser_damage.to(enemy_entity, DamageSource::Player));
+                match piercing.as_mut() {
+                    Some(piercing) if piercing.remaining > 0 => {
+                        piercing.remaining -= 1;
+                        piercing.hit.push(enemy_entity);
+                    }
+                    _ => {
+                        commands.entity(laser_entity).insert(Despawn);
+                        break;
+                    }
                 }
             }
         }
@@ -288,37 +488,31 @@ fn player_laser_hit_enemy_system(
 
 fn enemy_laser_hit_player_system(
     mut commands: Commands,
-    mut player_state: ResMut<PlayerState>,
-    time: Res<Time>,
-    laser_query: Query<(Entity, &Transform, &SpriteSize, &Damage, &FromEnemy), (With<FromEnemy>,With<Laser>)>,
-    mut player_query: Query<(Entity, &Transform, &SpriteSize), With<Player>>,
+    player_state: Res<PlayerState>,
+    mut damage_events: EventWriter<DamageEvent>,
+    laser_query: Query<(Entity, &Transform, &SpriteSize, &Damage, Option<&ParentEntity>), (With<FromEnemy>,With<Laser>)>,
+    player_query: Query<(Entity, &Transform, &SpriteSize, Option<&Immunity>), With<Player>>,
 ) {
-    if player_state.immunity_cooldown.tick(time.delta()).finished() {
-        if let Ok((player_entity, player_tf, player_size,)) = player_query.get_single_mut() {
-            let player_scale = Vec2::from(player_tf.scale.xy());
-            for (laser_entity, laser_tf, laser_size, laser_damage, from_enemy) in laser_query.iter() {
-                let laser_scale = Vec2::from(laser_tf.scale.xy());
-                // Detect collision
-                let collision = collide(
-                    laser_tf.translation,
-                    laser_size.0 * laser_scale,
-                    player_tf.translation,
-                    player_size.0 * player_scale
-                );
-
-                if let Some(_) = collision {
-                    player_state.health.hp -= laser_damage.damage_dealt();
-                    if player_state.health.hp <= 0. {
-                        commands.entity(player_entity).despawn();
-                        player_state.on = false;
-                        // player_state.shot(time.seconds_since_startup());
-                        commands.entity(laser_entity).despawn();
-                        commands.spawn().insert(ExplosionToSpawn(player_tf.translation.clone()));
-                    break;
-                    } else {
-                        commands.entity(laser_entity).despawn();
-                    }
-                }
+    if let Ok((player_entity, player_tf, player_size, immunity)) = player_query.get_single() {
+        // lasers fly straight through an immune player
+        if player_state.god || immunity.is_some_and(Immunity::active) {
+            return;
+        }
+        let player_scale = Vec2::from(player_tf.scale.xy());
+        for (laser_entity, laser_tf, laser_size, laser_damage, parent) in laser_query.iter() {
+            let laser_scale = Vec2::from(laser_tf.scale.xy());
+            // Detect collision
+            let collision = collide(
+                laser_tf.translation,
+                laser_size.0 * laser_scale,
+                player_tf.translation,
+                player_size.0 * player_scale
+            );
+
+            if let Some(_) = collision {
+                let source = DamageSource::Enemy(parent.map_or(laser_entity, |parent| parent.entity));
+                damage_events.send(laser_damage.to(player_entity, source));
+                commands.entity(laser_entity).insert(Despawn);
             }
         }
     }
@@ -342,21 +536,21 @@ fn explosion_to_spawn_system(
             .insert(Explosion)
             .insert(ExplosionTimer::default());
 
-        commands.entity(explosion_to_spawn_entity).despawn();
+        commands.entity(explosion_to_spawn_entity).insert(Despawn);
     }
 }
 
 fn explosion_animation_system(
     mut commands: Commands,
-    time: Res<Time>,
+    game_time: Res<GameTime>,
     mut query: Query<(Entity, &mut ExplosionTimer, &mut TextureAtlasSprite), With<Explosion>>,
 ) {
     for (entity, mut timer, mut sprite) in query.iter_mut() {
-        timer.0.tick(time.delta());
+        timer.0.tick(game_time.delta());
         if timer.0.finished() {
             sprite.index += 1; // Move to next sprite
             if sprite.index >= EXPLOSION_LEN {
-                commands.entity(entity).despawn();
+                commands.entity(entity).insert(Despawn);
             }
         }
     }
@@ -367,7 +561,3 @@ fn text_score_system(time: Res<Time>, player_state: Res<PlayerState>, mut query:
         text.sections[0].value = player_state.score.to_string();
     }
 }
-
-fn enemy_enrage_system(time: Res<Time>, mut query: Query<&mut Enemy, With<Enemy>>) {
-
-}
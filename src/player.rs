@@ -1,35 +1,74 @@
 use bevy::{prelude::*, time::FixedTimestep};
 
 use crate::{
-    components::{FromPlayer, Laser, Movable, Player, SpriteSize, Velocity, Health, Damage},
+    components::{FromPlayer, Laser, Movable, Player, SpriteSize, Velocity, Health, Damage, DamageKind, Immunity},
     GameTextures, WinSize, BASE_SPEED, PLAYER_LASER_SIZE, PLAYER_SIZE, PLAYER_SPRITE, SPRITE_SCALE,
-    TIME_STEP, PlayerState, PLAYER_RESPAWN_DELAY, player,
+    TIME_STEP, PlayerState, PLAYER_RESPAWN_DELAY, player, GameTime, events::ProjectileFired,
+    console::{parse_arg, ConsoleAppExt},
+    input::{PlayerActions, PlayerInputLabel},
+    projectile::{self, ProjectileType, Shooter},
+    shield::{Shield, ShieldAbility},
+    status::StatusEffects,
+    tuning::Tuning,
 };
 
 use std::f32::consts::PI;
 // const BASE_ROTATION_ANGLE_RAD: f32 = PI/2.;
 const ACCELERATION: f32 = 1.0;
 const MAX_VELOCITY: f32 = 15.0;
+const DRAG: f32 = 0.9;
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
+        let tuning = app.world.get_resource::<Tuning>().cloned().unwrap_or_default();
         app
-        .insert_resource(PlayerState::default())
+        .insert_resource(PlayerState::new(&tuning))
         .add_system(player_spawn_system)
-        .add_system(player_keyboard_event_system)
-        .add_system(player_fire_system);
+        .add_system(player_movement_system.after(PlayerInputLabel))
+        .add_system(player_fire_system.after(player_movement_system))
+        .register_console_command("god", "god", god_command)
+        .register_console_command("set hp", "set hp <hp>", set_hp_command)
+        .register_console_command("set projectile", "set projectile <type>", set_projectile_command)
+        .register_console_command("set damage", "set damage <kinetic|fire|ice|acid>", set_damage_command);
     }
 }
 
+fn god_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let mut player_state = world.resource_mut::<PlayerState>();
+    player_state.god = !player_state.god;
+    Ok(format!("god mode {}", if player_state.god { "on" } else { "off" }))
+}
+
+fn set_hp_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let hp: f32 = parse_arg(args, 0, "set hp <hp>")?;
+    let mut query = world.query_filtered::<&mut Health, With<Player>>();
+    let mut health = query.get_single_mut(world).map_err(|_| "no player".to_string())?;
+    health.hp = hp;
+    Ok(format!("player hp set to {}", hp))
+}
+
+fn set_projectile_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let projectile: ProjectileType = parse_arg(args, 0, "set projectile <type>")?;
+    world.resource_mut::<Tuning>().player_projectile = projectile;
+    Ok(format!("player projectile set to {:?}", projectile))
+}
+
+fn set_damage_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let kind: DamageKind = parse_arg(args, 0, "set damage <kinetic|fire|ice|acid>")?;
+    world.resource_mut::<Tuning>().player_damage_kind = kind;
+    Ok(format!("player damage set to {:?}", kind))
+}
+
 fn player_spawn_system(
     mut commands: Commands,
     mut player_state: ResMut<PlayerState>,
-    time: Res<Time>,
+    game_time: Res<GameTime>,
     game_textures: Res<GameTextures>,
     win_size: Res<WinSize>,
+    tuning: Res<Tuning>,
 ) {
-    if !player_state.on && player_state.spawn_cooldown.tick(time.delta()).finished() {
+    if !player_state.on && player_state.spawn_cooldown.tick(game_time.delta()).finished() {
         player_state.spawned();
         // add player
         let bottom = -win_size.h / 2.;
@@ -48,65 +87,48 @@ fn player_spawn_system(
             .insert(Movable {
                 auto_despawn: false,
             })
-            .insert(Velocity { x: 0., y: 0. });
-        player_state.health = Health {hp: 3., multiplier: 1.};
+            .insert(Velocity { x: 0., y: 0. })
+            .insert(Health {hp: tuning.player_hp, multiplier: 1.})
+            .insert(StatusEffects::default())
+            .insert(Shield::empty(tuning.player_shield, None))
+            .insert(ShieldAbility::new(tuning.player_shield_duration, tuning.player_shield_cooldown))
+            // spawn protection, then a short grace period after each hit
+            .insert(Immunity::new(tuning.player_immunity, tuning.player_hit_immunity));
     }
     
 }
 
-fn player_keyboard_event_system(
-    kb: Res<Input<KeyCode>>,
+fn player_movement_system(
+    actions: Res<PlayerActions>,
     win_size: Res<WinSize>,
     mut player_state: ResMut<PlayerState>,
-    mut query: Query<(&mut Velocity, &mut Transform), With<Player>>,
-    time: Res<Time>
+    mut query: Query<(&mut Velocity, &mut Transform, Option<&StatusEffects>), With<Player>>,
+    game_time: Res<GameTime>,
 ) {
-    if let Ok((mut velocity, mut transform)) = query.get_single_mut() {
-        if kb.pressed(KeyCode::A) { 
-            player_state.delta_x -= ACCELERATION;
-        } 
-        if kb.pressed(KeyCode::D) {
-            player_state.delta_x += ACCELERATION;
-        } 
-        if kb.pressed(KeyCode::S) {
-            player_state.delta_y -= ACCELERATION;
-        } 
-        if kb.pressed(KeyCode::W) {
-            player_state.delta_y += ACCELERATION;
-        } 
+    if game_time.is_stopped() {
+        return;
+    }
+    let scale = game_time.time_scale();
+    if let Ok((mut velocity, mut transform, status)) = query.get_single_mut() {
+        let thrust = actions.movement.clamp(Vec2::splat(-1.), Vec2::ONE) * status.map_or(1., StatusEffects::speed);
+        player_state.delta_x += thrust.x * ACCELERATION * scale;
+        player_state.delta_y += thrust.y * ACCELERATION * scale;
 
         player_state.delta_x = player_state.delta_x.clamp(-MAX_VELOCITY, MAX_VELOCITY);
-        transform.translation.x += player_state.delta_x;
+        transform.translation.x += player_state.delta_x * scale;
         player_state.delta_y = player_state.delta_y.clamp(-MAX_VELOCITY, MAX_VELOCITY);
-        transform.translation.y += player_state.delta_y;
-
-        // transform.translation.x = transform.translation.x.clamp(-320.0, 320.0);
-        // transform.translation.y = transform.translation.y.clamp(-320.0, 320.0);
+        transform.translation.y += player_state.delta_y * scale;
 
         // Decelerate
-        player_state.delta_x *= 0.9;
-        player_state.delta_y *= 0.9;
+        let drag = DRAG.powf(scale);
+        player_state.delta_x *= drag;
+        player_state.delta_y *= drag;
         // Fire angle
         let curr_angle = player_state.angle;
-        if kb.pressed(KeyCode::Up) {
-            player_state.angle = 0.;
-            // angle = 0.;
-        }
-        if kb.pressed(KeyCode::Down) {
-            player_state.angle = PI;
-            // angle = PI;
+        if let Some(aim) = actions.aim {
+            player_state.angle = aim;
         }
-        if kb.pressed(KeyCode::Left) {
-            player_state.angle = -PI/2.;
-            // angle = -PI/2.;
-        }
-        if kb.pressed(KeyCode::Right) {
-            player_state.angle = PI/2.;
-            // angle = PI/2.;
-        } 
-        if (kb.any_pressed(vec![KeyCode::Up,KeyCode::Down,KeyCode::Left,KeyCode::Right])) {
-            player_state.firing = true;
-        } else {player_state.firing = false}
+        player_state.firing = actions.aim.is_some();
 
         if curr_angle != player_state.angle {
             transform.rotate_z(curr_angle-player_state.angle);
@@ -114,41 +136,49 @@ fn player_keyboard_event_system(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn player_fire_system(
     mut commands: Commands,
     mut player_state: ResMut<PlayerState>,
     kb: Res<Input<KeyCode>>,
     game_textures: Res<GameTextures>,
-    query: Query<(&Transform, &Velocity), With<Player>>,
-    time: Res<Time>,
+    query: Query<(&Transform, &Velocity, Option<&StatusEffects>), With<Player>>,
+    game_time: Res<GameTime>,
+    tuning: Res<Tuning>,
+    mut fired_events: EventWriter<ProjectileFired>,
 ) {
     // let mut fired = false;
-    if let Ok((player_tf, vel)) = query.get_single() {
-        if player_state.fire_cooldown.tick(time.delta()).finished() {
+    if let Ok((player_tf, vel, status)) = query.get_single() {
+        // frozen guns don't cool down
+        if status.is_some_and(StatusEffects::is_frozen) {
+            return;
+        }
+        if player_state.fire_cooldown.tick(game_time.delta()).finished() {
             if player_state.firing { //|| kb.just_pressed(KeyCode::Space)
-                let (x, y) = (player_tf.translation.x, player_tf.translation.y);
-                let x_offset: f32 = PLAYER_SIZE.0 / 2. * SPRITE_SCALE - 5.;
-
-                let mut spawn_laser = |x_offset: f32| {
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            texture: game_textures.player_laser.clone(),
-                            transform: Transform {
-                                translation: Vec3::new(x + x_offset, y, 0.),
-                                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
-                                // rotation: Quat::from_rotation_z(player_tf.rotation.z.to_radians()), 
-                                rotation: player_tf.rotation                              
-                            },
-                            ..Default::default()
-                        })
-                        .insert(FromPlayer)
-                        .insert(Laser)
-                        .insert(Damage{dmg:10.,multiplier:1.,limit:5.})
-                        .insert(SpriteSize::from(PLAYER_LASER_SIZE))
-                        .insert(Movable { auto_despawn: true })
-                        .insert(Velocity { x: player_state.angle.sin() + player_state.delta_x/50., y: player_state.angle.cos() + player_state.delta_y/50. });
-                };
-                spawn_laser(0.);
+                let direction = Vec2::new(player_state.angle.sin(), player_state.angle.cos());
+                let spawned = projectile::fire(
+                    &mut commands,
+                    &game_textures,
+                    &tuning.player_projectile.def(),
+                    Shooter::Player {
+                        velocity: Vec2::new(player_state.delta_x, player_state.delta_y) / 50.,
+                    },
+                    player_tf.translation,
+                    direction,
+                    Damage {
+                        dmg: tuning.player_laser_damage,
+                        multiplier: 1.,
+                        limit: tuning.player_laser_damage,
+                        kind: tuning.player_damage_kind,
+                        crit_chance: tuning.player_crit_chance,
+                    },
+                );
+                for _ in spawned {
+                    fired_events.send(ProjectileFired {
+                        from_player: true,
+                        position: player_tf.translation,
+                    });
+                }
                 player_state.fire_cooldown.reset();
             }
         }
@@ -0,0 +1,292 @@
+use std::{f32::consts::TAU, str::FromStr};
+
+use bevy::prelude::*;
+
+use crate::{
+    components::{
+        Damage, Despawn, Enemy, FromEnemy, FromPlayer, Homing, Laser, Lifetime, Movable, ParentEntity,
+        Piercing, Player, Spin, SpriteSize, Velocity, Wave,
+    },
+    GameTextures, GameTime, BASE_SPEED, ENEMY_LASER_SIZE, PLAYER_LASER_SIZE, SPRITE_SCALE,
+};
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(homing_system)
+            .add_system(spin_system)
+            .add_system(wave_system)
+            .add_system(lifetime_system);
+    }
+}
+
+/// Named projectile definitions, selectable from tuning and archetype data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProjectileType {
+    #[default]
+    Laser,
+    Spread,
+    Homing,
+    Piercing,
+    Spinning,
+    Sine,
+}
+
+impl FromStr for ProjectileType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "laser" => Ok(ProjectileType::Laser),
+            "spread" => Ok(ProjectileType::Spread),
+            "homing" => Ok(ProjectileType::Homing),
+            "piercing" => Ok(ProjectileType::Piercing),
+            "spinning" => Ok(ProjectileType::Spinning),
+            "sine" => Ok(ProjectileType::Sine),
+            _ => Err(format!("unknown projectile type '{}'", s)),
+        }
+    }
+}
+
+/// How one trigger pull turns into projectiles and how they fly.
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileDef {
+    /// Projectiles per shot, fanned evenly across `spread`.
+    pub count: u32,
+    /// Total fan angle in radians.
+    pub spread: f32,
+    /// Multiple of `BASE_SPEED`.
+    pub speed: f32,
+    /// Max turn towards the nearest target in radians per second, 0 for none.
+    pub homing: f32,
+    /// Extra enemies passed through before the projectile is spent.
+    pub pierce: u32,
+    /// Heading rotation in radians per second.
+    pub spin: f32,
+    /// Weave `(amplitude, frequency)` in pixels and hertz.
+    pub wave: Option<(f32, f32)>,
+    /// Seconds before the projectile fizzles, limiting its range.
+    pub lifetime: Option<f32>,
+}
+
+impl Default for ProjectileDef {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            spread: 0.,
+            speed: 1.,
+            homing: 0.,
+            pierce: 0,
+            spin: 0.,
+            wave: None,
+            lifetime: None,
+        }
+    }
+}
+
+impl ProjectileType {
+    pub fn def(&self) -> ProjectileDef {
+        match self {
+            ProjectileType::Laser => ProjectileDef::default(),
+            ProjectileType::Spread => ProjectileDef {
+                count: 3,
+                spread: 0.5,
+                lifetime: Some(1.5),
+                ..default()
+            },
+            ProjectileType::Homing => ProjectileDef {
+                speed: 0.8,
+                homing: 3.,
+                lifetime: Some(3.),
+                ..default()
+            },
+            ProjectileType::Piercing => ProjectileDef {
+                speed: 1.4,
+                pierce: 2,
+                ..default()
+            },
+            ProjectileType::Spinning => ProjectileDef {
+                spin: 2.5,
+                lifetime: Some(2.),
+                ..default()
+            },
+            ProjectileType::Sine => ProjectileDef {
+                wave: Some((30., 2.)),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Who fired, deciding the sprite, the hit side and the inherited velocity.
+#[derive(Clone, Copy)]
+pub enum Shooter {
+    Player { velocity: Vec2 },
+    Enemy(Entity),
+}
+
+fn heading_rotation(direction: Vec2) -> Quat {
+    Quat::from_rotation_z((-direction.x).atan2(direction.y))
+}
+
+/// Spawn one shot of `def` from `position` towards `direction`, returns the
+/// projectiles spawned.
+pub fn fire(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    def: &ProjectileDef,
+    shooter: Shooter,
+    position: Vec3,
+    direction: Vec2,
+    damage: Damage,
+) -> Vec<Entity> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec2::ZERO {
+        return Vec::new();
+    }
+    let count = def.count.max(1);
+    let mut spawned = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let offset = if count > 1 {
+            def.spread * (i as f32 / (count - 1) as f32 - 0.5)
+        } else {
+            0.
+        };
+        let heading = Vec2::from_angle(offset).rotate(direction);
+        let mut velocity = heading * def.speed;
+        let (texture, size) = match shooter {
+            Shooter::Player { velocity: inherited } => {
+                velocity += inherited;
+                (game_textures.player_laser.clone(), PLAYER_LASER_SIZE)
+            }
+            Shooter::Enemy(_) => (game_textures.enemy_laser.clone(), ENEMY_LASER_SIZE),
+        };
+
+        let mut projectile = commands.spawn_bundle(SpriteBundle {
+            texture,
+            transform: Transform {
+                translation: Vec3::new(position.x, position.y, 0.),
+                rotation: heading_rotation(velocity),
+                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.),
+            },
+            ..Default::default()
+        });
+        projectile
+            .insert(Laser)
+            .insert(damage)
+            .insert(SpriteSize::from(size))
+            .insert(Movable { auto_despawn: true })
+            .insert(Velocity { x: velocity.x, y: velocity.y });
+        match shooter {
+            Shooter::Player { .. } => {
+                projectile.insert(FromPlayer);
+            }
+            Shooter::Enemy(parent) => {
+                projectile.insert(FromEnemy).insert(ParentEntity { entity: parent });
+            }
+        }
+        if def.homing > 0. {
+            projectile.insert(Homing { turn_rate: def.homing });
+        }
+        if def.pierce > 0 {
+            projectile.insert(Piercing {
+                remaining: def.pierce,
+                hit: Vec::new(),
+            });
+        }
+        if def.spin != 0. {
+            projectile.insert(Spin(def.spin));
+        }
+        if let Some((amplitude, frequency)) = def.wave {
+            projectile.insert(Wave {
+                direction: velocity.normalize_or_zero(),
+                speed: velocity.length(),
+                amplitude,
+                frequency,
+                age: 0.,
+            });
+        }
+        if let Some(lifetime) = def.lifetime {
+            projectile.insert(Lifetime(Timer::from_seconds(lifetime, false)));
+        }
+        spawned.push(projectile.id());
+    }
+    spawned
+}
+
+fn homing_system(
+    game_time: Res<GameTime>,
+    mut query: Query<(&mut Velocity, &mut Transform, &Homing, Option<&FromPlayer>), With<Laser>>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Laser>)>,
+    player_query: Query<&Transform, (With<Player>, Without<Laser>)>,
+) {
+    let dt = game_time.delta_seconds();
+    if dt == 0. {
+        return;
+    }
+    for (mut velocity, mut transform, homing, from_player) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let target = if from_player.is_some() {
+            enemy_query
+                .iter()
+                .map(|tf| tf.translation.truncate())
+                .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+        } else {
+            player_query.get_single().ok().map(|tf| tf.translation.truncate())
+        };
+        let current = Vec2::new(velocity.x, velocity.y);
+        let desired = match target {
+            Some(target) => target - position,
+            None => continue,
+        };
+        if current == Vec2::ZERO || desired == Vec2::ZERO {
+            continue;
+        }
+        let max_turn = homing.turn_rate * dt;
+        let turn = current.angle_between(desired).clamp(-max_turn, max_turn);
+        let turned = Vec2::from_angle(turn).rotate(current);
+        (velocity.x, velocity.y) = (turned.x, turned.y);
+        transform.rotation = heading_rotation(turned);
+    }
+}
+
+fn spin_system(
+    game_time: Res<GameTime>,
+    mut query: Query<(&mut Velocity, &mut Transform, &Spin)>,
+) {
+    let dt = game_time.delta_seconds();
+    for (mut velocity, mut transform, spin) in query.iter_mut() {
+        let turned = Vec2::from_angle(spin.0 * dt).rotate(Vec2::new(velocity.x, velocity.y));
+        (velocity.x, velocity.y) = (turned.x, turned.y);
+        transform.rotation = heading_rotation(turned);
+    }
+}
+
+fn wave_system(
+    game_time: Res<GameTime>,
+    mut query: Query<(&mut Velocity, &mut Transform, &mut Wave)>,
+) {
+    let dt = game_time.delta_seconds();
+    for (mut velocity, mut transform, mut wave) in query.iter_mut() {
+        wave.age += dt;
+        // derivative of amplitude * sin(2πft), in BASE_SPEED units
+        let omega = TAU * wave.frequency;
+        let lateral = wave.amplitude * omega * (omega * wave.age).cos() / BASE_SPEED;
+        let moved = wave.direction * wave.speed + wave.direction.perp() * lateral;
+        (velocity.x, velocity.y) = (moved.x, moved.y);
+        transform.rotation = heading_rotation(moved);
+    }
+}
+
+fn lifetime_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    mut query: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in query.iter_mut() {
+        if lifetime.0.tick(game_time.delta()).just_finished() {
+            commands.entity(entity).insert(Despawn);
+        }
+    }
+}
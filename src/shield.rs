@@ -0,0 +1,227 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{
+    components::{Player, SpriteSize},
+    input::{PlayerActions, PlayerInputLabel},
+    GameTime,
+};
+
+// Bubble radius relative to half the sprite's longer side
+const BUBBLE_PADDING: f32 = 1.15;
+const BUBBLE_COLOR: Color = Color::rgba(0.4, 0.8, 1., 0.);
+// Bubble alpha when nearly drained, and when fully charged
+const BUBBLE_ALPHA: (f32, f32) = (0.1, 0.35);
+// Just above the sprite it surrounds
+const BUBBLE_Z: f32 = 1.;
+
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(shield_setup_system)
+            .add_system(shield_ability_system.after(PlayerInputLabel))
+            .add_system(shield_regen_system)
+            .add_system(shield_bubble_spawn_system)
+            .add_system(shield_bubble_system);
+    }
+}
+
+/// Recharging of a shield left alone for a while.
+#[derive(Clone, Copy, Debug)]
+pub struct ShieldRegen {
+    /// Seconds without a hit before recharging starts.
+    pub delay: f32,
+    /// Shield points per second.
+    pub rate: f32,
+}
+
+/// Absorbing layer in front of `Health`: hits drain it first and only what
+/// gets past it hurts. Status effects don't catch while it's up.
+#[derive(Component)]
+pub struct Shield {
+    pub hp: f32,
+    pub max: f32,
+    /// `None` stays drained once broken.
+    pub regen: Option<ShieldRegen>,
+    since_hit: f32,
+}
+
+impl Shield {
+    /// Fully charged.
+    pub fn new(max: f32, regen: Option<ShieldRegen>) -> Self {
+        Self {
+            hp: max,
+            max,
+            regen,
+            since_hit: 0.,
+        }
+    }
+
+    /// Down until raised.
+    pub fn empty(max: f32, regen: Option<ShieldRegen>) -> Self {
+        Self {
+            hp: 0.,
+            ..Self::new(max, regen)
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.hp > 0.
+    }
+
+    pub fn raise(&mut self) {
+        self.hp = self.max;
+        self.since_hit = 0.;
+    }
+
+    pub fn lower(&mut self) {
+        self.hp = 0.;
+    }
+
+    /// Soak up as much of `amount` as is left, returns the rest.
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let absorbed = amount.min(self.hp);
+        self.hp -= absorbed;
+        self.since_hit = 0.;
+        amount - absorbed
+    }
+
+    fn share(&self) -> f32 {
+        if self.max > 0. {
+            (self.hp / self.max).clamp(0., 1.)
+        } else {
+            0.
+        }
+    }
+}
+
+/// The player's shield as an ability: raising it charges the `Shield` for
+/// `duration` seconds, and it can be raised again `cooldown` seconds after.
+#[derive(Component)]
+pub struct ShieldAbility {
+    duration: f32,
+    cooldown: Timer,
+    active: Option<Timer>,
+}
+
+impl ShieldAbility {
+    /// Ready from the start.
+    pub fn new(duration: f32, cooldown: f32) -> Self {
+        let mut cooldown = Timer::from_seconds(cooldown, false);
+        cooldown.set_elapsed(cooldown.duration());
+        Self {
+            duration,
+            cooldown,
+            active: None,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.active.is_none() && self.cooldown.finished()
+    }
+
+    pub fn cooldown(&self) -> &Timer {
+        &self.cooldown
+    }
+}
+
+/// Circle drawn around every shielded entity while its shield is up.
+#[derive(Component)]
+struct ShieldBubble;
+
+struct ShieldMesh(Mesh2dHandle);
+
+fn shield_setup_system(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let mesh = meshes.add(Mesh::from(shape::Circle::new(1.)));
+    commands.insert_resource(ShieldMesh(Mesh2dHandle(mesh)));
+}
+
+fn shield_ability_system(
+    game_time: Res<GameTime>,
+    actions: Res<PlayerActions>,
+    mut query: Query<(&mut Shield, &mut ShieldAbility), With<Player>>,
+) {
+    for (mut shield, mut ability) in query.iter_mut() {
+        let ability = &mut *ability;
+        ability.cooldown.tick(game_time.delta());
+        if let Some(timer) = &mut ability.active {
+            // runs out, or breaks early
+            if timer.tick(game_time.delta()).finished() || !shield.is_up() {
+                shield.lower();
+                ability.active = None;
+            }
+        } else if actions.shield && ability.cooldown.finished() {
+            shield.raise();
+            ability.active = Some(Timer::from_seconds(ability.duration, false));
+            ability.cooldown.reset();
+        }
+    }
+}
+
+fn shield_regen_system(game_time: Res<GameTime>, mut query: Query<&mut Shield>) {
+    let dt = game_time.delta_seconds();
+    for mut shield in query.iter_mut() {
+        let regen = match shield.regen {
+            Some(regen) => regen,
+            None => continue,
+        };
+        shield.since_hit += dt;
+        if shield.since_hit >= regen.delay && shield.hp < shield.max {
+            shield.hp = (shield.hp + regen.rate * dt).min(shield.max);
+        }
+    }
+}
+
+fn shield_bubble_spawn_system(
+    mut commands: Commands,
+    mesh: Res<ShieldMesh>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, Option<&SpriteSize>), Added<Shield>>,
+) {
+    for (entity, size) in query.iter() {
+        let radius = size.map_or(1., |size| size.0.max_element() / 2.) * BUBBLE_PADDING;
+        let bubble = commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: mesh.0.clone(),
+                material: materials.add(ColorMaterial::from(BUBBLE_COLOR)),
+                transform: Transform {
+                    translation: Vec3::new(0., 0., BUBBLE_Z),
+                    scale: Vec3::new(radius, radius, 1.),
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(ShieldBubble)
+            .id();
+        commands.entity(entity).add_child(bubble);
+    }
+}
+
+/// Shows bubbles while their shield is up, fading as it drains.
+fn shield_bubble_system(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    shield_query: Query<&Shield>,
+    mut bubble_query: Query<(&Parent, &Handle<ColorMaterial>, &mut Visibility), With<ShieldBubble>>,
+) {
+    for (parent, material, mut visibility) in bubble_query.iter_mut() {
+        let shield = match shield_query.get(parent.get()) {
+            Ok(shield) => shield,
+            Err(_) => continue,
+        };
+        if visibility.is_visible != shield.is_up() {
+            visibility.is_visible = shield.is_up();
+        }
+        let alpha = BUBBLE_ALPHA.0 + (BUBBLE_ALPHA.1 - BUBBLE_ALPHA.0) * shield.share();
+        // only touch the asset when it shows, re-uploading is not free
+        let stale = materials.get(material).is_some_and(|current| (current.color.a() - alpha).abs() > 0.01);
+        if stale {
+            if let Some(current) = materials.get_mut(material) {
+                current.color.set_a(alpha);
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    process,
+    time::Duration,
+};
+
+use crate::{
+    build_app, cli::Args, input::InputSource, stats::GameStats, AppOptions, GameTime, PlayerState,
+    TIME_STEP,
+};
+
+const CSV_HEADER: &str =
+    "run,seed,survival_time,score,kills,damage_taken,waves_cleared,shots_fired,shots_hit";
+
+/// Batch balancing: plays `args.runs` headless games with the autopilot, one
+/// seed per run, until the player first dies or `max_time` game seconds pass,
+/// and writes one CSV row per run.
+pub fn run(args: &Args) {
+    let base_seed = args.seed.unwrap_or(0);
+    let file = File::create(&args.out).unwrap_or_else(|err| {
+        eprintln!("cannot create {}: {}", args.out, err);
+        process::exit(1);
+    });
+    let mut out = BufWriter::new(file);
+    let write = |out: &mut BufWriter<File>, line: String| {
+        if let Err(err) = writeln!(out, "{}", line) {
+            eprintln!("cannot write {}: {}", args.out, err);
+            process::exit(1);
+        }
+    };
+    write(&mut out, CSV_HEADER.to_string());
+
+    for run in 0..args.runs {
+        let seed = base_seed + run as u64;
+        let mut app = build_app(&AppOptions {
+            headless: true,
+            quiet: true,
+            seed: Some(seed),
+            tuning: args.tuning.clone(),
+        });
+        app.insert_resource(InputSource::Autopilot);
+        app.world.resource_mut::<GameTime>().fixed_step = Some(Duration::from_secs_f32(TIME_STEP));
+
+        let survival_time = loop {
+            app.update();
+            let elapsed = app.world.resource::<GameTime>().seconds_since_startup();
+            if app.world.resource::<GameStats>().deaths > 0 || elapsed >= args.max_time {
+                break elapsed;
+            }
+        };
+
+        let stats = app.world.resource::<GameStats>();
+        let score = app.world.resource::<PlayerState>().score;
+        write(
+            &mut out,
+            format!(
+                "{},{},{:.2},{},{},{},{},{},{}",
+                run,
+                seed,
+                survival_time,
+                score,
+                stats.kills,
+                stats.damage_taken,
+                stats.waves_cleared,
+                stats.player_shots,
+                stats.player_hits,
+            ),
+        );
+        eprintln!("run {}/{} seed {}: {:.1}s, {} kills", run + 1, args.runs, seed, survival_time, stats.kills);
+    }
+    if let Err(err) = out.flush() {
+        eprintln!("cannot write {}: {}", args.out, err);
+        process::exit(1);
+    }
+}
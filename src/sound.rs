@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{play_queued_audio_system, AudioOutput},
+    prelude::*,
+    reflect::TypeUuid,
+};
+
+use crate::events::{EnemyKilled, PlayerDamaged, ProjectileFired};
+
+const SAMPLE_RATE: u32 = 44_100;
+// Square waves are loud, this keeps a blip at a comfortable level
+const BLIP_GAIN: f32 = 0.2;
+// Enemies fire a lot more than the player, keep them in the background
+const ENEMY_LASER_VOLUME: f32 = 0.3;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Blip>()
+            .init_resource::<Audio<Blip>>()
+            .init_non_send_resource::<AudioOutput<Blip>>()
+            .add_system_to_stage(CoreStage::PostUpdate, play_queued_audio_system::<Blip>)
+            .add_startup_system(sound_setup_system)
+            .add_system(sound_listener_system);
+    }
+}
+
+/// Placeholder sound effect generated on the fly until recorded ones exist:
+/// a square wave sliding from `from_hz` to `to_hz` while it fades out.
+#[derive(Clone, Copy, Debug, TypeUuid)]
+#[uuid = "c1e63661-e92a-4e8d-b64f-7ad93f1cd6c8"]
+pub struct Blip {
+    pub from_hz: f32,
+    pub to_hz: f32,
+    pub seconds: f32,
+}
+
+impl Decodable for Blip {
+    type Decoder = BlipSamples;
+    type DecoderItem = f32;
+
+    fn decoder(&self) -> BlipSamples {
+        BlipSamples {
+            blip: *self,
+            index: 0,
+            phase: 0.,
+        }
+    }
+}
+
+pub struct BlipSamples {
+    blip: Blip,
+    index: u32,
+    phase: f32,
+}
+
+impl Iterator for BlipSamples {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let progress = self.index as f32 / SAMPLE_RATE as f32 / self.blip.seconds;
+        if progress >= 1. {
+            return None;
+        }
+        self.index += 1;
+        let hz = self.blip.from_hz + (self.blip.to_hz - self.blip.from_hz) * progress;
+        self.phase = (self.phase + hz / SAMPLE_RATE as f32).fract();
+        let square = if self.phase < 0.5 { 1. } else { -1. };
+        Some(square * (1. - progress) * BLIP_GAIN)
+    }
+}
+
+impl rodio::Source for BlipSamples {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.blip.seconds))
+    }
+}
+
+struct GameSounds {
+    enemy_killed: Handle<Blip>,
+    player_damaged: Handle<Blip>,
+    laser: Handle<Blip>,
+}
+
+fn sound_setup_system(mut commands: Commands, mut blips: ResMut<Assets<Blip>>, windows: Res<Windows>) {
+    // headless runs go much faster than real time, nobody wants to hear that
+    if windows.get_primary().is_none() {
+        return;
+    }
+    commands.insert_resource(GameSounds {
+        enemy_killed: blips.add(Blip {
+            from_hz: 400.,
+            to_hz: 60.,
+            seconds: 0.35,
+        }),
+        player_damaged: blips.add(Blip {
+            from_hz: 220.,
+            to_hz: 110.,
+            seconds: 0.25,
+        }),
+        laser: blips.add(Blip {
+            from_hz: 1400.,
+            to_hz: 500.,
+            seconds: 0.08,
+        }),
+    });
+}
+
+/// At most one of each sound per frame, however many events came in.
+fn sound_listener_system(
+    audio: Res<Audio<Blip>>,
+    sounds: Option<Res<GameSounds>>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut damaged_events: EventReader<PlayerDamaged>,
+    mut fired_events: EventReader<ProjectileFired>,
+) {
+    let sounds = match sounds {
+        Some(sounds) => sounds,
+        None => return,
+    };
+    if killed_events.iter().count() > 0 {
+        audio.play(sounds.enemy_killed.clone());
+    }
+    if damaged_events.iter().count() > 0 {
+        audio.play(sounds.player_damaged.clone());
+    }
+    let (mut player_fired, mut enemy_fired) = (false, false);
+    for event in fired_events.iter() {
+        if event.from_player {
+            player_fired = true;
+        } else {
+            enemy_fired = true;
+        }
+    }
+    if player_fired {
+        audio.play(sounds.laser.clone());
+    }
+    if enemy_fired {
+        audio.play_with_settings(sounds.laser.clone(), PlaybackSettings::ONCE.with_volume(ENEMY_LASER_VOLUME));
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::events::{DamageSource, EnemyHit, EnemyKilled, EnemySpawned, PlayerDamaged, PlayerDied, ProjectileFired, WaveCleared};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GameStats::default())
+            .add_system(stats_listener_system);
+    }
+}
+
+/// Running totals for the current session, fed purely from gameplay events.
+#[derive(Default, Debug, Clone)]
+pub struct GameStats {
+    pub kills: u32,
+    pub enemies_spawned: u32,
+    pub damage_taken: f32,
+    pub deaths: u32,
+    pub player_shots: u32,
+    pub player_hits: u32,
+    pub enemy_shots: u32,
+    pub waves_cleared: u32,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stats_listener_system(
+    mut stats: ResMut<GameStats>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut spawned_events: EventReader<EnemySpawned>,
+    mut hit_events: EventReader<EnemyHit>,
+    mut damaged_events: EventReader<PlayerDamaged>,
+    mut died_events: EventReader<PlayerDied>,
+    mut fired_events: EventReader<ProjectileFired>,
+    mut cleared_events: EventReader<WaveCleared>,
+) {
+    stats.kills += killed_events.iter().count() as u32;
+    stats.enemies_spawned += spawned_events.iter().count() as u32;
+    // a burn ticking is not the player landing a shot
+    stats.player_hits += hit_events
+        .iter()
+        .filter(|event| !matches!(event.source, DamageSource::Burning))
+        .count() as u32;
+    stats.deaths += died_events.iter().count() as u32;
+    stats.waves_cleared += cleared_events.iter().count() as u32;
+    for event in damaged_events.iter() {
+        stats.damage_taken += event.amount;
+    }
+    for event in fired_events.iter() {
+        if event.from_player {
+            stats.player_shots += 1;
+        } else {
+            stats.enemy_shots += 1;
+        }
+    }
+}
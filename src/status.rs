@@ -0,0 +1,284 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    components::{DamageKind, Player},
+    damage::DamageLabel,
+    enemy::Formation,
+    events::{DamageEvent, DamageSource},
+    GameTime,
+};
+
+// Share of a fire hit dealt again every second while burning
+const BURN_SHARE: f32 = 0.3;
+const BURN_SECONDS: f32 = 3.;
+// Seconds between burn damage ticks
+const BURN_TICK: f32 = 0.5;
+// Movement speed multiplier while slowed
+const SLOW_FACTOR: f32 = 0.5;
+const SLOW_SECONDS: f32 = 2.;
+// Ice hits landing within one slow that freeze the target
+const FREEZE_CHILL: u32 = 3;
+const FREEZE_SECONDS: f32 = 1.5;
+// Armour stripped per acid hit, all of it grows back when the acid wears off
+const ACID_SHRED: f32 = 1.;
+const ACID_SECONDS: f32 = 4.;
+const ICON_SIZE: f32 = 28.;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(status_hud_setup_system)
+            .add_system(status_system.before(DamageLabel))
+            .add_system(status_hud_system)
+            // after everything else that colours sprites this frame
+            .add_system_to_stage(CoreStage::PostUpdate, status_tint_system);
+    }
+}
+
+/// The lingering effects elemental hits leave, in order of which one tints
+/// the sprite when several are active.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Frozen,
+    Burning,
+    Corroded,
+    Slowed,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [Status::Frozen, Status::Burning, Status::Corroded, Status::Slowed];
+
+    fn tint(&self) -> Color {
+        match self {
+            Status::Frozen => Color::rgb(0.55, 0.85, 1.),
+            Status::Burning => Color::rgb(1., 0.6, 0.2),
+            Status::Corroded => Color::rgb(0.55, 1., 0.35),
+            Status::Slowed => Color::rgb(0.7, 0.75, 1.),
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Status::Frozen => "F",
+            Status::Burning => "B",
+            Status::Corroded => "A",
+            Status::Slowed => "S",
+        }
+    }
+}
+
+/// Status effects currently on an entity. Only entities carrying this pick
+/// them up: the player and regular enemies, bosses shrug them off.
+#[derive(Component, Default)]
+pub struct StatusEffects {
+    burning: Option<Timer>,
+    burn_dps: f32,
+    burn_tick: Timer,
+    slowed: Option<Timer>,
+    /// Ice hits taken during the current slow.
+    chill: u32,
+    frozen: Option<Timer>,
+    corroded: Option<Timer>,
+    corrosion: f32,
+    /// Formation speed from before the slow, put back when it wears off.
+    base_speed: Option<f32>,
+    tinted: bool,
+}
+
+impl StatusEffects {
+    /// Leave the effect of a `kind` hit worth `amount`, after resistances.
+    pub fn apply(&mut self, kind: DamageKind, amount: f32) {
+        match kind {
+            DamageKind::Kinetic => {}
+            DamageKind::Fire => {
+                if self.burning.is_none() {
+                    self.burn_tick = Timer::from_seconds(BURN_TICK, true);
+                }
+                self.burning = Some(Timer::from_seconds(BURN_SECONDS, false));
+                self.burn_dps = self.burn_dps.max(amount * BURN_SHARE);
+            }
+            DamageKind::Ice => {
+                self.slowed = Some(Timer::from_seconds(SLOW_SECONDS, false));
+                if self.frozen.is_none() {
+                    self.chill += 1;
+                    if self.chill >= FREEZE_CHILL {
+                        self.chill = 0;
+                        self.frozen = Some(Timer::from_seconds(FREEZE_SECONDS, false));
+                    }
+                }
+            }
+            DamageKind::Acid => {
+                self.corroded = Some(Timer::from_seconds(ACID_SECONDS, false));
+                self.corrosion += ACID_SHRED;
+            }
+        }
+    }
+
+    pub fn has(&self, status: Status) -> bool {
+        match status {
+            Status::Frozen => self.frozen.is_some(),
+            Status::Burning => self.burning.is_some(),
+            Status::Corroded => self.corroded.is_some(),
+            Status::Slowed => self.slowed.is_some(),
+        }
+    }
+
+    /// Frozen guns: fire cooldowns stand still.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Multiplier on movement speed.
+    pub fn speed(&self) -> f32 {
+        if self.slowed.is_some() {
+            SLOW_FACTOR
+        } else {
+            1.
+        }
+    }
+
+    /// Armour currently eaten away by acid.
+    pub fn corrosion(&self) -> f32 {
+        self.corrosion
+    }
+}
+
+/// Tick an effect, dropping it once it runs out. True if it just did.
+fn wear_off(effect: &mut Option<Timer>, delta: Duration) -> bool {
+    let done = effect.as_mut().is_some_and(|timer| timer.tick(delta).finished());
+    if done {
+        *effect = None;
+    }
+    done
+}
+
+fn status_system(
+    game_time: Res<GameTime>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects, Option<&mut Formation>)>,
+) {
+    let delta = game_time.delta();
+    for (entity, mut status, formation) in query.iter_mut() {
+        let status = &mut *status;
+        if status.burning.is_some() && status.burn_tick.tick(delta).just_finished() {
+            damage_events.send(DamageEvent {
+                target: entity,
+                source: DamageSource::Burning,
+                amount: status.burn_dps * BURN_TICK,
+                kind: DamageKind::Fire,
+                crit_chance: 0.,
+            });
+        }
+        if wear_off(&mut status.burning, delta) {
+            status.burn_dps = 0.;
+        }
+        if wear_off(&mut status.slowed, delta) {
+            status.chill = 0;
+        }
+        wear_off(&mut status.frozen, delta);
+        if wear_off(&mut status.corroded, delta) {
+            status.corrosion = 0.;
+        }
+
+        if let Some(mut formation) = formation {
+            match (status.slowed.is_some(), status.base_speed) {
+                (true, None) => {
+                    status.base_speed = Some(formation.speed);
+                    formation.speed *= SLOW_FACTOR;
+                }
+                (false, Some(speed)) => {
+                    formation.speed = speed;
+                    status.base_speed = None;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn status_tint_system(mut query: Query<(&mut StatusEffects, &mut Sprite)>) {
+    for (mut status, mut sprite) in query.iter_mut() {
+        match Status::ALL.into_iter().find(|effect| status.has(*effect)) {
+            Some(effect) => {
+                sprite.color = effect.tint();
+                status.tinted = true;
+            }
+            None if status.tinted => {
+                sprite.color = Color::WHITE;
+                status.tinted = false;
+            }
+            None => {}
+        }
+    }
+}
+
+/// HUD slot showing whether the player suffers from a status.
+#[derive(Component)]
+struct StatusIcon(Status);
+
+fn status_hud_setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(15.0),
+                    left: Val::Px(15.0),
+                    ..default()
+                },
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .with_children(|hud| {
+            for status in Status::ALL {
+                hud.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                        margin: UiRect {
+                            right: Val::Px(6.0),
+                            ..default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: UiColor(status.tint()),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(StatusIcon(status))
+                .with_children(|icon| {
+                    icon.spawn_bundle(TextBundle {
+                        text: Text::from_section(
+                            status.icon(),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 22.0,
+                                color: Color::BLACK,
+                            },
+                        ),
+                        ..default()
+                    });
+                });
+            }
+        });
+}
+
+fn status_hud_system(
+    player_query: Query<&StatusEffects, With<Player>>,
+    mut icon_query: Query<(&StatusIcon, &mut Visibility)>,
+) {
+    let status = player_query.get_single().ok();
+    for (icon, mut visibility) in icon_query.iter_mut() {
+        let visible = status.is_some_and(|status| status.has(icon.0));
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
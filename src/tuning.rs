@@ -0,0 +1,130 @@
+use crate::{arena::BoundaryMode, components::DamageKind, difficulty::DifficultyPreset, projectile::ProjectileType};
+
+/// Gameplay numbers that balancing runs may override (`--set key=value`).
+#[derive(Clone, Debug)]
+pub struct Tuning {
+    pub player_hp: f32,
+    pub player_fire_cooldown: f32,
+    /// Seconds of immunity after spawning.
+    pub player_immunity: f32,
+    /// Seconds of immunity after each hit taken.
+    pub player_hit_immunity: f32,
+    pub player_respawn: f32,
+    pub player_laser_damage: f32,
+    /// Chance from 0 to 1 of a player shot doing critical damage.
+    pub player_crit_chance: f32,
+    pub player_projectile: ProjectileType,
+    /// Damage the shield ability absorbs before breaking.
+    pub player_shield: f32,
+    /// Seconds the shield lasts once raised.
+    pub player_shield_duration: f32,
+    /// Seconds from raising the shield until it can be raised again.
+    pub player_shield_cooldown: f32,
+    /// Element of player shots.
+    pub player_damage_kind: DamageKind,
+    /// Preset the difficulty curve starts from.
+    pub difficulty: DifficultyPreset,
+    /// How the play-field edges start out, F3 cycles it in game.
+    pub boundary: BoundaryMode,
+    pub enemy_hp_multiplier: f32,
+    pub enemy_fire_cooldown: f32,
+    pub enemy_laser_damage: f32,
+    pub enemy_laser_damage_late: f32,
+    /// Seconds between the spawn director's decisions.
+    pub enemy_spawn_interval: f32,
+    /// Director budget earned per second at the start, before ramping up.
+    pub director_budget_rate: f32,
+    /// Most enemy cost allowed on the field at once in the first wave.
+    pub director_max_threat: f32,
+    /// Kills that end a wave and bring on its boss.
+    pub wave_kills: f32,
+    /// 0 to 1, scales how well enemies lead their shots and how tightly they group.
+    pub enemy_aim_skill: f32,
+    /// BulletML `$rank`, 0 (easiest) to 1.
+    pub bulletml_rank: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            player_hp: 3.,
+            player_fire_cooldown: 0.5,
+            player_immunity: 4.,
+            player_hit_immunity: 1.,
+            player_respawn: 2.,
+            player_laser_damage: 10.,
+            player_crit_chance: 0.1,
+            player_projectile: ProjectileType::Laser,
+            player_shield: 3.,
+            player_shield_duration: 3.,
+            player_shield_cooldown: 12.,
+            player_damage_kind: DamageKind::Kinetic,
+            difficulty: DifficultyPreset::Normal,
+            boundary: BoundaryMode::Clamp,
+            enemy_hp_multiplier: 1.,
+            enemy_fire_cooldown: 1.,
+            enemy_laser_damage: 1.,
+            enemy_laser_damage_late: 10.,
+            enemy_spawn_interval: 1.5,
+            director_budget_rate: 0.6,
+            director_max_threat: 6.,
+            wave_kills: 15.,
+            enemy_aim_skill: 1.,
+            bulletml_rank: 0.5,
+        }
+    }
+}
+
+impl Tuning {
+    /// Apply a `key=value` override.
+    pub fn apply(&mut self, assignment: &str) -> Result<(), String> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got '{}'", assignment))?;
+        if key.trim() == "player_projectile" {
+            self.player_projectile = value.trim().parse()?;
+            return Ok(());
+        }
+        if key.trim() == "player_damage_kind" {
+            self.player_damage_kind = value.trim().parse()?;
+            return Ok(());
+        }
+        if key.trim() == "difficulty" {
+            self.difficulty = value.trim().parse()?;
+            return Ok(());
+        }
+        if key.trim() == "boundary" {
+            self.boundary = value.trim().parse()?;
+            return Ok(());
+        }
+        let value: f32 = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", value))?;
+        let field = match key.trim() {
+            "player_hp" => &mut self.player_hp,
+            "player_fire_cooldown" => &mut self.player_fire_cooldown,
+            "player_immunity" => &mut self.player_immunity,
+            "player_hit_immunity" => &mut self.player_hit_immunity,
+            "player_respawn" => &mut self.player_respawn,
+            "player_laser_damage" => &mut self.player_laser_damage,
+            "player_crit_chance" => &mut self.player_crit_chance,
+            "player_shield" => &mut self.player_shield,
+            "player_shield_duration" => &mut self.player_shield_duration,
+            "player_shield_cooldown" => &mut self.player_shield_cooldown,
+            "enemy_hp_multiplier" => &mut self.enemy_hp_multiplier,
+            "enemy_fire_cooldown" => &mut self.enemy_fire_cooldown,
+            "enemy_laser_damage" => &mut self.enemy_laser_damage,
+            "enemy_laser_damage_late" => &mut self.enemy_laser_damage_late,
+            "enemy_spawn_interval" => &mut self.enemy_spawn_interval,
+            "director_budget_rate" => &mut self.director_budget_rate,
+            "director_max_threat" => &mut self.director_max_threat,
+            "wave_kills" => &mut self.wave_kills,
+            "enemy_aim_skill" => &mut self.enemy_aim_skill,
+            "bulletml_rank" => &mut self.bulletml_rank,
+            other => return Err(format!("unknown tuning key '{}'", other)),
+        };
+        *field = value;
+        Ok(())
+    }
+}